@@ -0,0 +1,105 @@
+use crate::idioma::{Idioma, tr};
+use crate::reglas::Regla;
+use crate::resultados::AlTerminar;
+use crate::salida::{Colision, PLANTILLA_PREDETERMINADA};
+use directories::ProjectDirs;
+use eframe::egui;
+use serde::{Deserialize, Serialize};
+use std::path::PathBuf;
+
+/// How many recently processed reports are remembered.
+const MAXIMO_RECIENTES: usize = 10;
+
+/// Folders where the app keeps its config and data on this platform.
+pub fn dirs() -> Option<ProjectDirs> {
+    ProjectDirs::from("co.edu", "eafit", "reps-sia")
+}
+
+/// File where eframe keeps the window state and `Ajustes`, next to the
+/// other user config files.
+pub fn ruta_predeterminada() -> Option<PathBuf> {
+    dirs().map(|dirs| dirs.config_dir().join("ajustes.ron"))
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, Serialize, Deserialize)]
+pub enum Tema {
+    #[default]
+    Sistema,
+    Claro,
+    Oscuro,
+}
+
+impl Tema {
+    pub const TODOS: [Tema; 3] = [Tema::Sistema, Tema::Claro, Tema::Oscuro];
+
+    pub fn nombre(self) -> &'static str {
+        tr(match self {
+            Tema::Sistema => "Del sistema",
+            Tema::Claro => "Claro",
+            Tema::Oscuro => "Oscuro",
+        })
+    }
+
+    pub fn aplicar(self, ctx: &egui::Context) {
+        ctx.set_theme(match self {
+            Tema::Sistema => egui::ThemePreference::System,
+            Tema::Claro => egui::ThemePreference::Light,
+            Tema::Oscuro => egui::ThemePreference::Dark,
+        });
+    }
+}
+
+/// Window preferences remembered between sessions.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(default)]
+pub struct Ajustes {
+    pub carpeta_entrada: Option<PathBuf>,
+    pub carpeta_salida: Option<PathBuf>,
+    pub plantilla: String,
+    pub colision: Colision,
+    pub al_terminar: AlTerminar,
+    pub escribir_txt: bool,
+    pub traza: bool,
+    pub consolidar: bool,
+    pub revisar: bool,
+    pub correcciones: Option<PathBuf>,
+    /// Request type last open in the rules tab.
+    pub regla: Regla,
+    pub tema: Tema,
+    pub idioma: Idioma,
+    /// Reports processed lately, the newest first.
+    pub recientes: Vec<PathBuf>,
+    /// Folder last watched for new reports.
+    pub carpeta_vigilada: Option<PathBuf>,
+}
+
+impl Default for Ajustes {
+    fn default() -> Self {
+        Self {
+            carpeta_entrada: None,
+            carpeta_salida: None,
+            plantilla: PLANTILLA_PREDETERMINADA.to_string(),
+            colision: Colision::default(),
+            al_terminar: AlTerminar::default(),
+            escribir_txt: false,
+            traza: false,
+            consolidar: false,
+            revisar: false,
+            correcciones: None,
+            regla: Regla::Cea,
+            tema: Tema::default(),
+            idioma: Idioma::default(),
+            recientes: Vec::new(),
+            carpeta_vigilada: None,
+        }
+    }
+}
+
+/// Puts `paths` first in `recientes`, dropping repeats and the oldest ones.
+pub fn agregar_recientes(recientes: &mut Vec<PathBuf>, paths: &[PathBuf]) {
+    recientes.retain(|reciente| !paths.contains(reciente));
+    for path in paths.iter().rev() {
+        recientes.insert(0, path.clone());
+    }
+    recientes.truncate(MAXIMO_RECIENTES);
+}
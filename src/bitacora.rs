@@ -0,0 +1,268 @@
+use crate::ajustes;
+use crate::idioma::{tr, trf};
+use eframe::egui;
+use log::{Level, LevelFilter, Log, Metadata, Record};
+use std::cell::RefCell;
+use std::fs::{File, OpenOptions};
+use std::io::Write;
+use std::path::{Path, PathBuf};
+use std::sync::{Mutex, OnceLock};
+
+/// Size at which the log file is rotated.
+const TAMANO_MAXIMO: u64 = 1024 * 1024;
+/// Rotated files kept, `reps-sia.1.log` being the newest.
+const ANTERIORES: usize = 3;
+/// Entries kept in memory for the panel; the file has all of them.
+const MAXIMO_ENTRADAS: usize = 1000;
+
+/// Log message, with the file being processed when it was logged.
+#[derive(Debug, Clone)]
+pub struct Entrada {
+    pub nivel: Level,
+    pub archivo: Option<String>,
+    pub mensaje: String,
+}
+
+struct Bitacora {
+    ruta: Option<PathBuf>,
+    archivo: Mutex<Option<File>>,
+    entradas: Mutex<Vec<Entrada>>,
+    /// Echo warnings and errors to stderr, for the command line.
+    consola: bool,
+}
+
+static BITACORA: OnceLock<Bitacora> = OnceLock::new();
+
+thread_local! {
+    static PROCESANDO: RefCell<Option<String>> = const { RefCell::new(None) };
+}
+
+fn ruta_predeterminada() -> Option<PathBuf> {
+    ajustes::dirs().map(|dirs| dirs.data_dir().join("reps-sia.log"))
+}
+
+fn ruta_anterior(ruta: &Path, n: usize) -> PathBuf {
+    ruta.with_extension(format!("{n}.log"))
+}
+
+/// Moves `reps-sia.log` to `reps-sia.1.log`, and so on, dropping the oldest.
+fn rotar(ruta: &Path) {
+    for n in (1..ANTERIORES).rev() {
+        let _ = std::fs::rename(ruta_anterior(ruta, n), ruta_anterior(ruta, n + 1));
+    }
+    let _ = std::fs::rename(ruta, ruta_anterior(ruta, 1));
+}
+
+fn abrir(ruta: &Path) -> Option<File> {
+    if let Some(dir) = ruta.parent() {
+        std::fs::create_dir_all(dir).ok()?;
+    }
+    if std::fs::metadata(ruta).is_ok_and(|meta| meta.len() >= TAMANO_MAXIMO) {
+        rotar(ruta);
+    }
+    OpenOptions::new().create(true).append(true).open(ruta).ok()
+}
+
+impl Log for Bitacora {
+    /// Only this app's messages; those of eframe, wgpu and the like aren't
+    /// for the user.
+    fn enabled(&self, metadata: &Metadata) -> bool {
+        metadata.level() <= Level::Info && metadata.target().starts_with(env!("CARGO_CRATE_NAME"))
+    }
+
+    fn log(&self, record: &Record) {
+        if !self.enabled(record.metadata()) {
+            return;
+        }
+        let archivo = PROCESANDO.with_borrow(Clone::clone);
+        let mensaje = record.args().to_string().trim_end().to_string();
+        let contexto = archivo
+            .as_deref()
+            .map(|archivo| format!(" [{archivo}]"))
+            .unwrap_or_default();
+
+        if self.consola && record.level() <= Level::Warn {
+            eprintln!("{}{contexto}: {mensaje}", record.level());
+        }
+
+        if let Some(ruta) = &self.ruta {
+            let mut archivo = self.archivo.lock().expect("log file lock");
+            if archivo
+                .as_ref()
+                .is_some_and(|f| f.metadata().is_ok_and(|meta| meta.len() >= TAMANO_MAXIMO))
+            {
+                *archivo = None;
+                rotar(ruta);
+            }
+            if archivo.is_none() {
+                *archivo = abrir(ruta);
+            }
+            if let Some(f) = archivo.as_mut() {
+                let _ = writeln!(
+                    f,
+                    "{} {:<5}{contexto} {mensaje}",
+                    chrono::Local::now().format("%Y-%m-%d %H:%M:%S"),
+                    record.level(),
+                );
+            }
+        }
+
+        let mut entradas = self.entradas.lock().expect("log entries lock");
+        if entradas.len() >= MAXIMO_ENTRADAS {
+            entradas.remove(0);
+        }
+        entradas.push(Entrada {
+            nivel: record.level(),
+            archivo,
+            mensaje,
+        });
+    }
+
+    fn flush(&self) {
+        if let Some(f) = self.archivo.lock().expect("log file lock").as_mut() {
+            let _ = f.flush();
+        }
+    }
+}
+
+/// Starts logging to the rolling file and, with `consola`, to stderr.
+pub fn iniciar(consola: bool) {
+    let ruta = ruta_predeterminada();
+    let bitacora = BITACORA.get_or_init(|| Bitacora {
+        archivo: Mutex::new(ruta.as_deref().and_then(abrir)),
+        ruta,
+        entradas: Mutex::new(Vec::new()),
+        consola,
+    });
+    if log::set_logger(bitacora).is_ok() {
+        log::set_max_level(LevelFilter::Info);
+    }
+}
+
+/// Tags what this thread logs with `path` until the guard is dropped.
+pub struct Procesando(Option<String>);
+
+pub fn procesando(path: &Path) -> Procesando {
+    let nombre = path
+        .file_name()
+        .unwrap_or_default()
+        .to_string_lossy()
+        .to_string();
+    Procesando(PROCESANDO.replace(Some(nombre)))
+}
+
+impl Drop for Procesando {
+    fn drop(&mut self) {
+        PROCESANDO.set(self.0.take());
+    }
+}
+
+fn entradas() -> Vec<Entrada> {
+    BITACORA
+        .get()
+        .map(|bitacora| bitacora.entradas.lock().expect("log entries lock").clone())
+        .unwrap_or_default()
+}
+
+fn limpiar() {
+    if let Some(bitacora) = BITACORA.get() {
+        bitacora.entradas.lock().expect("log entries lock").clear();
+    }
+}
+
+fn color(ui: &egui::Ui, nivel: Level) -> egui::Color32 {
+    match nivel {
+        Level::Error => ui.visuals().error_fg_color,
+        Level::Warn => ui.visuals().warn_fg_color,
+        _ => ui.visuals().text_color(),
+    }
+}
+
+/// Collapsible panel with the log of this session, grouped by file.
+pub fn ui(ui: &mut egui::Ui) {
+    let entradas = entradas();
+    let advertencias = entradas
+        .iter()
+        .filter(|entrada| entrada.nivel <= Level::Warn)
+        .count();
+    egui::CollapsingHeader::new(trf("Registro ({} advertencias)", &[&advertencias]))
+        .id_salt("bitacora")
+        .show(ui, |ui| {
+            ui.horizontal(|ui| {
+                if ui.button(tr("Limpiar")).clicked() {
+                    limpiar();
+                }
+                if let Some(ruta) = BITACORA.get().and_then(|bitacora| bitacora.ruta.as_ref())
+                    && ui.button(tr("Abrir archivo de registro")).clicked()
+                    && let Err(e) = open::that_detached(ruta)
+                {
+                    log::error!("Error opening '{}': {e}", ruta.display());
+                }
+            });
+
+            // Groups in the order files were first seen.
+            let mut grupos: Vec<(Option<&str>, Vec<&Entrada>)> = Vec::new();
+            for entrada in &entradas {
+                let archivo = entrada.archivo.as_deref();
+                match grupos.iter_mut().find(|(a, _)| *a == archivo) {
+                    Some((_, grupo)) => grupo.push(entrada),
+                    None => grupos.push((archivo, vec![entrada])),
+                }
+            }
+
+            egui::ScrollArea::vertical()
+                .id_salt("bitacora")
+                .max_height(200.0)
+                .show(ui, |ui| {
+                    for (archivo, grupo) in grupos {
+                        let advertencias = grupo
+                            .iter()
+                            .filter(|entrada| entrada.nivel <= Level::Warn)
+                            .count();
+                        let titulo = match archivo {
+                            Some(archivo) => {
+                                trf("{} ({} advertencias)", &[&archivo, &advertencias])
+                            }
+                            None => trf("General ({} advertencias)", &[&advertencias]),
+                        };
+                        egui::CollapsingHeader::new(titulo)
+                            .id_salt(("bitacora", archivo))
+                            .show(ui, |ui| {
+                                for entrada in grupo {
+                                    let primera =
+                                        entrada.mensaje.lines().next().unwrap_or_default();
+                                    ui.colored_label(
+                                        color(ui, entrada.nivel),
+                                        format!("{}: {primera}", entrada.nivel),
+                                    )
+                                    .on_hover_text(&entrada.mensaje);
+                                }
+                            });
+                    }
+                });
+        });
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn metadata(target: &str, level: Level) -> Metadata<'_> {
+        Metadata::builder().target(target).level(level).build()
+    }
+
+    #[test]
+    fn enabled_solo_deja_pasar_los_mensajes_de_la_app() {
+        let bitacora = Bitacora {
+            ruta: None,
+            archivo: Mutex::new(None),
+            entradas: Mutex::new(Vec::new()),
+            consola: false,
+        };
+
+        assert!(bitacora.enabled(&metadata(module_path!(), Level::Info)));
+        assert!(!bitacora.enabled(&metadata(module_path!(), Level::Debug)));
+        assert!(!bitacora.enabled(&metadata("wgpu_core::device", Level::Warn)));
+        assert!(!bitacora.enabled(&metadata("eframe", Level::Info)));
+    }
+}
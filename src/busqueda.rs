@@ -0,0 +1,221 @@
+use crate::idioma::{tr, trf};
+use crate::pdf_handling::{ANOTACIONES, DECISION, NOTAS, Reporte, Solicitud, campos_del_tipo};
+use eframe::egui;
+use std::path::PathBuf;
+
+/// Request picked in the results, whose details are shown.
+#[derive(Debug, Clone, PartialEq)]
+struct Seleccion {
+    tipo: String,
+    numero_solicitud: String,
+}
+
+/// Search over every request loaded in this session.
+#[derive(Debug, Default)]
+pub struct Busqueda {
+    /// Reports processed in this session, the newest last.
+    reportes: Vec<Reporte>,
+    consulta: String,
+    seleccion: Option<Seleccion>,
+}
+
+/// Lowercase without accents, so "perez" finds "Pérez".
+fn normalizar(texto: &str) -> String {
+    texto
+        .to_lowercase()
+        .chars()
+        .map(|c| match c {
+            'á' | 'à' | 'ä' => 'a',
+            'é' | 'è' | 'ë' => 'e',
+            'í' | 'ì' | 'ï' => 'i',
+            'ó' | 'ò' | 'ö' => 'o',
+            'ú' | 'ù' | 'ü' => 'u',
+            'ñ' => 'n',
+            c => c,
+        })
+        .collect()
+}
+
+fn coincide(solicitud: &Solicitud, consulta: &str) -> bool {
+    [
+        solicitud.nombre_del_estudiante.as_str(),
+        solicitud.numero_solicitud.as_str(),
+        solicitud.plan_de_estudios.as_str(),
+        solicitud.motivos.as_deref().unwrap_or_default(),
+    ]
+    .into_iter()
+    .any(|texto| normalizar(texto).contains(consulta))
+        || solicitud.identificacion.to_string().contains(consulta)
+}
+
+/// Names of the reports the request appears in.
+fn archivos(reporte: &Reporte, solicitud: &Solicitud) -> String {
+    solicitud
+        .origenes
+        .iter()
+        .filter_map(|&origen| reporte.procedencias.get(origen))
+        .map(|procedencia| procedencia.archivo())
+        .collect::<Vec<_>>()
+        .join(", ")
+}
+
+fn pdfs(reporte: &Reporte) -> Vec<&PathBuf> {
+    reporte
+        .procedencias
+        .iter()
+        .map(|procedencia| &procedencia.pdf_path)
+        .collect()
+}
+
+impl Busqueda {
+    /// Adds a processed report, replacing an earlier run over the same files.
+    pub fn cargar(&mut self, reporte: Reporte) {
+        let nuevos = pdfs(&reporte);
+        if let Some(i) = self.reportes.iter().position(|r| pdfs(r) == nuevos) {
+            self.reportes.remove(i);
+        }
+        self.reportes.push(reporte);
+    }
+
+    /// Search box, results and the details of the chosen one. `pendientes`
+    /// are the reports waiting in the review, searched as well.
+    pub fn ui<'a>(&mut self, ui: &mut egui::Ui, pendientes: impl Iterator<Item = &'a Reporte>) {
+        let Busqueda {
+            reportes: cargados,
+            consulta,
+            seleccion,
+        } = self;
+        ui.horizontal(|ui| {
+            ui.label(tr(
+                "Nombre, identificación, número de solicitud, plan o motivos:",
+            ));
+            ui.text_edit_singleline(consulta);
+        });
+
+        let mut reportes: Vec<&Reporte> = cargados.iter().collect();
+        for reporte in pendientes {
+            reportes.push(reporte);
+        }
+        let consulta = normalizar(consulta.trim());
+        let encontradas: Vec<(&Reporte, &str, &Solicitud)> = reportes
+            .iter()
+            .flat_map(|reporte| {
+                reporte
+                    .solicitudes
+                    .iter()
+                    .filter(|(tipo, _)| tipo.as_str() != ANOTACIONES)
+                    .flat_map(move |(tipo, solicitudes)| {
+                        solicitudes
+                            .iter()
+                            .map(move |solicitud| (*reporte, tipo.as_str(), solicitud))
+                    })
+            })
+            .filter(|(_, _, solicitud)| consulta.is_empty() || coincide(solicitud, &consulta))
+            .collect();
+
+        if reportes.is_empty() {
+            ui.label(tr("Procese reportes para buscar entre sus solicitudes"));
+            return;
+        }
+        ui.label(trf(
+            "{} solicitudes encontradas en {} reportes",
+            &[&encontradas.len(), &reportes.len()],
+        ));
+
+        egui::ScrollArea::both()
+            .id_salt("busqueda")
+            .max_height(250.0)
+            .show(ui, |ui| {
+                egui::Grid::new("busqueda")
+                    .striped(true)
+                    .num_columns(6)
+                    .show(ui, |ui| {
+                        for header in [
+                            tr("Número de solicitud"),
+                            tr("Tipo"),
+                            tr("Identificación"),
+                            tr("Nombre del estudiante"),
+                            tr("Plan de estudios"),
+                            tr("Reportes"),
+                        ] {
+                            ui.strong(header);
+                        }
+                        ui.end_row();
+                        for (reporte, tipo, solicitud) in &encontradas {
+                            let elegible = Seleccion {
+                                tipo: tipo.to_string(),
+                                numero_solicitud: solicitud.numero_solicitud.clone(),
+                            };
+                            let elegida = seleccion.as_ref() == Some(&elegible);
+                            if ui
+                                .selectable_label(elegida, &solicitud.numero_solicitud)
+                                .clicked()
+                            {
+                                *seleccion = Some(elegible);
+                            }
+                            ui.label(tr(tipo));
+                            ui.label(solicitud.identificacion.to_string());
+                            ui.label(&solicitud.nombre_del_estudiante);
+                            ui.label(&solicitud.plan_de_estudios);
+                            ui.label(archivos(reporte, solicitud));
+                            ui.end_row();
+                        }
+                    });
+            });
+
+        let Some(seleccion) = seleccion else {
+            return;
+        };
+        let Some((reporte, solicitud)) = reportes.iter().find_map(|reporte| {
+            reporte
+                .solicitudes
+                .get(&seleccion.tipo)?
+                .iter()
+                .find(|solicitud| solicitud.numero_solicitud == seleccion.numero_solicitud)
+                .map(|solicitud| (*reporte, solicitud))
+        }) else {
+            return;
+        };
+        ui.separator();
+        detalle(ui, reporte, &seleccion.tipo, solicitud);
+    }
+}
+
+/// Every field of a request, as in its row of the excel, and the text it was read from.
+fn detalle(ui: &mut egui::Ui, reporte: &Reporte, tipo: &str, solicitud: &Solicitud) {
+    ui.strong(trf(
+        "Solicitud {} ({})",
+        &[&solicitud.numero_solicitud, &tr(tipo)],
+    ));
+    egui::Grid::new("detalle").num_columns(2).show(ui, |ui| {
+        for campo in campos_del_tipo(tipo) {
+            ui.label(campo.encabezado());
+            let valor = solicitud.valor(campo).unwrap_or_default();
+            match solicitud.originales.get(&campo) {
+                Some(original) => ui
+                    .label(valor)
+                    .on_hover_text(trf("Valor en el PDF: {}", &[original])),
+                None => ui.label(valor),
+            };
+            ui.end_row();
+        }
+        for (nombre, valor) in [(DECISION, &solicitud.decision), (NOTAS, &solicitud.notas)] {
+            if !valor.is_empty() {
+                ui.label(tr(nombre));
+                ui.label(valor);
+                ui.end_row();
+            }
+        }
+        ui.label(tr("Reportes"));
+        ui.label(archivos(reporte, solicitud));
+        ui.end_row();
+    });
+    egui::CollapsingHeader::new(tr("Texto del reporte"))
+        .id_salt("detalle_texto")
+        .show(ui, |ui| {
+            egui::ScrollArea::vertical()
+                .id_salt("detalle_texto")
+                .max_height(150.0)
+                .show(ui, |ui| ui.label(&solicitud.texto_fuente));
+        });
+}
@@ -0,0 +1,322 @@
+use crate::historial::Historial;
+use crate::idioma::{self, Idioma};
+use crate::pdf_handling::{self, Opciones, Reporte};
+use crate::salida::{Colision, Destino, PLANTILLA_PREDETERMINADA};
+use crate::vigilancia::{INTERVALO_PREDETERMINADO, Vigilancia};
+use clap::{Parser, Subcommand};
+use std::path::{Path, PathBuf};
+use std::time::Duration;
+
+/// Opens the window when no subcommand is given.
+#[derive(Parser, Debug)]
+#[command(author = "Jorge A. VM", version, about = "SIA agenda report processor")]
+pub struct Cli {
+    #[command(subcommand)]
+    pub comando: Option<Comando>,
+}
+
+#[derive(Subcommand, Debug)]
+pub enum Comando {
+    /// Processes PDF reports, or their text (extracted .txt or a dump of unhandled requests)
+    Procesar {
+        /// Reports to process
+        #[arg(required = true)]
+        entradas: Vec<PathBuf>,
+
+        /// Output folder, or the xlsx for a single one; next to each report by default
+        #[arg(short = 'o', long, value_name = "RUTA")]
+        salida: Option<PathBuf>,
+
+        /// Name of each xlsx; {stem} is the report name and {fecha_reporte} its newest request date
+        #[arg(long, value_name = "PLANTILLA", default_value = PLANTILLA_PREDETERMINADA)]
+        plantilla: String,
+
+        /// Replace existing xlsx files instead of numbering the new ones
+        #[arg(long)]
+        sobrescribir: bool,
+
+        /// Merge every report into one xlsx
+        #[arg(long)]
+        consolidar: bool,
+
+        /// Also dump the unhandled requests to a txt
+        #[arg(long)]
+        txt: bool,
+
+        /// Also write a trace of how each report was parsed
+        #[arg(long)]
+        traza: bool,
+
+        /// Earlier reviewed workbook whose decisions and corrections are kept
+        #[arg(long, value_name = "ARCHIVO")]
+        libro_revisado: Option<PathBuf>,
+
+        /// Corrections file, the user's one by default if it exists
+        #[arg(long, value_name = "ARCHIVO")]
+        correcciones: Option<PathBuf>,
+
+        /// Language of the sheets and headers
+        #[arg(long, value_enum, default_value_t = Idioma::Es)]
+        idioma: Idioma,
+    },
+    /// Watches a folder and processes each new PDF until Ctrl+C, recording them in reps-sia-vigilancia.toml
+    Vigilar {
+        /// Folder where reports arrive
+        carpeta: PathBuf,
+
+        /// Output folder, the watched one by default
+        #[arg(short = 'o', long, value_name = "CARPETA")]
+        salida: Option<PathBuf>,
+
+        /// Seconds between looks at the folder
+        #[arg(long, value_name = "SEGUNDOS", default_value_t = INTERVALO_PREDETERMINADO)]
+        intervalo: u64,
+
+        /// Name of each xlsx; {stem} is the report name and {fecha_reporte} its newest request date
+        #[arg(long, value_name = "PLANTILLA", default_value = PLANTILLA_PREDETERMINADA)]
+        plantilla: String,
+
+        /// Replace existing xlsx files instead of numbering the new ones
+        #[arg(long)]
+        sobrescribir: bool,
+
+        /// Also dump the unhandled requests to a txt
+        #[arg(long)]
+        txt: bool,
+
+        /// Corrections file, the user's one by default if it exists
+        #[arg(long, value_name = "ARCHIVO")]
+        correcciones: Option<PathBuf>,
+
+        /// Language of the sheets and headers
+        #[arg(long, value_enum, default_value_t = Idioma::Es)]
+        idioma: Idioma,
+    },
+    /// Searches the history for every request of a student
+    Historial {
+        /// ID number, part of the name or request number
+        consulta: String,
+
+        /// History database, the user's one by default
+        #[arg(long, value_name = "ARCHIVO")]
+        db: Option<PathBuf>,
+    },
+}
+
+fn abrir_historial(db: Option<PathBuf>) -> Result<Historial, String> {
+    match db {
+        Some(path) => Historial::abrir(&path),
+        None => Historial::abrir_predeterminado(),
+    }
+}
+
+fn es_xlsx(path: &Path) -> bool {
+    path.extension()
+        .is_some_and(|ext| ext.eq_ignore_ascii_case("xlsx"))
+}
+
+/// Writes `reporte` to the file given with `-o`, or where `destino` says.
+fn exportar(
+    reporte: Reporte,
+    archivo: Option<&Path>,
+    destino: &Destino,
+    usadas: &mut Vec<PathBuf>,
+    opciones: &Opciones,
+) -> Result<(Reporte, PathBuf), String> {
+    let ruta = match archivo {
+        Some(archivo) => archivo.to_path_buf(),
+        None => destino.ruta(&reporte, usadas)?,
+    };
+    let reporte = pdf_handling::exportar(reporte, &ruta, opciones)?;
+    Ok((reporte, ruta))
+}
+
+fn resumen(reporte: &Reporte) -> String {
+    let solicitudes: usize = reporte
+        .solicitudes
+        .iter()
+        .filter(|(tipo, _)| tipo.as_str() != pdf_handling::ANOTACIONES)
+        .map(|(_, solicitudes)| solicitudes.len())
+        .sum();
+    let mut resumen = format!(
+        "{solicitudes} solicitudes, {} sin procesar",
+        reporte.sin_procesar.len()
+    );
+    if !reporte.conflictos.is_empty() {
+        resumen += &format!(", {} conflictos", reporte.conflictos.len());
+    }
+    resumen
+}
+
+/// Saves to the default history, only warning if it can't.
+fn registrar(reporte: &Reporte) {
+    if let Err(e) = Historial::abrir_predeterminado().and_then(|mut h| h.registrar(reporte)) {
+        log::warn!("Could not save to the history: {e}");
+    }
+}
+
+pub fn ejecutar(comando: Comando) -> Result<(), String> {
+    match comando {
+        Comando::Procesar {
+            entradas,
+            salida,
+            plantilla,
+            sobrescribir,
+            consolidar,
+            txt,
+            traza,
+            libro_revisado,
+            correcciones,
+            idioma,
+        } => {
+            idioma::usar(idioma);
+            let opciones = Opciones {
+                escribir_txt: txt,
+                libro_revisado,
+                correcciones,
+                traza,
+            };
+            let (archivo, carpeta) = match salida {
+                Some(salida) if es_xlsx(&salida) => (Some(salida), None),
+                // Consolidated runs used to go to the working directory.
+                None if consolidar => (None, Some(PathBuf::new())),
+                carpeta => (None, carpeta),
+            };
+            if archivo.is_some() && entradas.len() > 1 && !consolidar {
+                return Err(
+                    "Con varios reportes sin consolidar -o debe ser una carpeta".to_string()
+                );
+            }
+            let destino = Destino {
+                carpeta,
+                plantilla,
+                colision: if sobrescribir {
+                    Colision::Sobrescribir
+                } else {
+                    Colision::Sufijo
+                },
+            };
+            destino.validar()?;
+            let mut usadas = Vec::new();
+
+            if consolidar {
+                let reportes = entradas
+                    .iter()
+                    .map(|entrada| pdf_handling::analizar_con_opciones(entrada, &opciones))
+                    .collect::<Result<Vec<Reporte>, String>>()?;
+                let (reporte, ruta) = exportar(
+                    Reporte::consolidar(reportes),
+                    archivo.as_deref(),
+                    &destino,
+                    &mut usadas,
+                    &opciones,
+                )?;
+                println!("{}: {}", ruta.display(), resumen(&reporte));
+                registrar(&reporte);
+                return Ok(());
+            }
+
+            let mut fallidas = 0;
+            for entrada in &entradas {
+                let resultado =
+                    pdf_handling::analizar_con_opciones(entrada, &opciones).and_then(|reporte| {
+                        exportar(
+                            reporte,
+                            archivo.as_deref(),
+                            &destino,
+                            &mut usadas,
+                            &opciones,
+                        )
+                    });
+                match resultado {
+                    Ok((reporte, ruta)) => {
+                        println!("{}: {}", ruta.display(), resumen(&reporte));
+                        registrar(&reporte);
+                    }
+                    Err(e) => {
+                        log::error!("{}: {e}", entrada.display());
+                        fallidas += 1;
+                    }
+                }
+            }
+            if fallidas > 0 {
+                return Err(format!(
+                    "{fallidas} de {} reportes fallaron",
+                    entradas.len()
+                ));
+            }
+            Ok(())
+        }
+        Comando::Vigilar {
+            carpeta,
+            salida,
+            intervalo,
+            plantilla,
+            sobrescribir,
+            txt,
+            correcciones,
+            idioma,
+        } => {
+            idioma::usar(idioma);
+            let opciones = Opciones {
+                escribir_txt: txt,
+                libro_revisado: None,
+                correcciones,
+                traza: false,
+            };
+            let destino = Destino {
+                carpeta: salida,
+                plantilla,
+                colision: if sobrescribir {
+                    Colision::Sobrescribir
+                } else {
+                    Colision::Sufijo
+                },
+            };
+            let mut vigilancia = Vigilancia::nueva(carpeta, destino, opciones)?;
+            log::info!("Watching '{}'", vigilancia.entrada().display());
+            println!(
+                "Vigilando '{}', Ctrl+C para terminar",
+                vigilancia.entrada().display()
+            );
+            loop {
+                // Failures are logged and recorded, the watch goes on; so does
+                // it when the folder can't be read for a moment.
+                match vigilancia.revisar() {
+                    Ok(procesados) => {
+                        for procesado in procesados {
+                            if let Ok((reporte, ruta)) = procesado.resultado {
+                                println!("{}: {}", ruta.display(), resumen(&reporte));
+                                registrar(&reporte);
+                            }
+                        }
+                    }
+                    Err(e) => log::error!("{e}"),
+                }
+                std::thread::sleep(Duration::from_secs(intervalo));
+            }
+        }
+        Comando::Historial { consulta, db } => {
+            let historial = abrir_historial(db)?;
+            let registros = historial.buscar(&consulta)?;
+            if registros.is_empty() {
+                println!("No hay solicitudes para '{consulta}'");
+            }
+            for registro in registros {
+                println!(
+                    "{}\t{}\t{}\t{}\t{}\t{}",
+                    registro.numero_solicitud,
+                    registro.fecha_de_solicitud,
+                    registro.tipo,
+                    registro.identificacion,
+                    registro.nombre_del_estudiante,
+                    registro.plan_de_estudios,
+                );
+                if !registro.reportes.is_empty() {
+                    println!("\tReportes: {}", registro.reportes);
+                }
+            }
+            Ok(())
+        }
+    }
+}
@@ -0,0 +1,127 @@
+use crate::idioma::{tr, trf};
+use eframe::egui;
+use std::path::{Path, PathBuf};
+
+/// Extensions the app can read, as in the file picker.
+const EXTENSIONES: [&str; 2] = ["pdf", "txt"];
+
+/// Files picked or dropped on the window, waiting to be processed.
+#[derive(Debug, Default)]
+pub struct Cola {
+    archivos: Vec<PathBuf>,
+}
+
+fn tiene_extension(path: &Path, extensiones: &[&str]) -> bool {
+    path.extension()
+        .and_then(|ext| ext.to_str())
+        .is_some_and(|ext| extensiones.iter().any(|e| ext.eq_ignore_ascii_case(e)))
+}
+
+/// Every PDF under `dir`, sorted so folders queue in a predictable order.
+/// Links to folders aren't followed, so a link back up the tree can't loop.
+fn pdfs_en(dir: &Path, pdfs: &mut Vec<PathBuf>) -> Result<(), String> {
+    let entradas =
+        std::fs::read_dir(dir).map_err(|e| format!("Error reading '{}': {e}", dir.display()))?;
+    let mut paths: Vec<(PathBuf, bool)> = entradas
+        .filter_map(|entrada| {
+            let entrada = entrada.ok()?;
+            let es_carpeta = entrada.file_type().ok()?.is_dir();
+            Some((entrada.path(), es_carpeta))
+        })
+        .collect();
+    paths.sort();
+    for (path, es_carpeta) in paths {
+        if es_carpeta {
+            pdfs_en(&path, pdfs)?;
+        } else if tiene_extension(&path, &["pdf"]) {
+            pdfs.push(path);
+        }
+    }
+    Ok(())
+}
+
+impl Cola {
+    pub fn archivos(&self) -> &[PathBuf] {
+        &self.archivos
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.archivos.is_empty()
+    }
+
+    /// Queues files and the PDFs inside folders, skipping files already queued
+    /// and those that aren't reports. Returns how many were added.
+    pub fn agregar(&mut self, paths: impl IntoIterator<Item = PathBuf>) -> Result<usize, String> {
+        let antes = self.archivos.len();
+        for path in paths {
+            let nuevos = if path.is_dir() {
+                let mut pdfs = Vec::new();
+                pdfs_en(&path, &mut pdfs)?;
+                pdfs
+            } else if tiene_extension(&path, &EXTENSIONES) {
+                vec![path]
+            } else {
+                continue;
+            };
+            for nuevo in nuevos {
+                if !self.archivos.contains(&nuevo) {
+                    self.archivos.push(nuevo);
+                }
+            }
+        }
+        Ok(self.archivos.len() - antes)
+    }
+
+    pub fn ui(&mut self, ui: &mut egui::Ui) {
+        if self.archivos.is_empty() {
+            ui.label(tr("Arrastre aquí PDFs o carpetas, o selecciónelos"));
+            return;
+        }
+        ui.horizontal(|ui| {
+            ui.label(trf("{} archivos en cola", &[&self.archivos.len()]));
+            if ui.button(tr("Vaciar")).clicked() {
+                self.archivos.clear();
+            }
+        });
+        let mut quitar = None;
+        egui::ScrollArea::vertical()
+            .id_salt("cola")
+            .max_height(150.0)
+            .show(ui, |ui| {
+                for (i, path) in self.archivos.iter().enumerate() {
+                    ui.horizontal(|ui| {
+                        if ui.small_button(tr("Quitar")).clicked() {
+                            quitar = Some(i);
+                        }
+                        ui.label(path.display().to_string());
+                    });
+                }
+            });
+        if let Some(i) = quitar {
+            self.archivos.remove(i);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[cfg(unix)]
+    #[test]
+    fn pdfs_en_no_sigue_enlaces_a_carpetas() {
+        let dir = std::env::temp_dir().join(format!("reps-sia-{}-cola", std::process::id()));
+        let _ = std::fs::remove_dir_all(&dir);
+        std::fs::create_dir_all(dir.join("sub")).unwrap();
+        std::fs::write(dir.join("a.pdf"), b"").unwrap();
+        std::fs::write(dir.join("sub").join("b.pdf"), b"").unwrap();
+        std::os::unix::fs::symlink(&dir, dir.join("sub").join("bucle")).unwrap();
+
+        let mut pdfs = Vec::new();
+        let resultado = pdfs_en(&dir, &mut pdfs);
+        let _ = std::fs::remove_dir_all(&dir);
+
+        resultado.unwrap();
+        assert_eq!(pdfs, vec![dir.join("a.pdf"), dir.join("sub").join("b.pdf")]);
+    }
+}
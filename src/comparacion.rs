@@ -0,0 +1,438 @@
+use crate::idioma::tr;
+use crate::pdf_handling::{
+    ANOTACIONES, Campo, Procedencia, Reporte, SIN_PROCESAR, Solicitud, write_procedencia,
+};
+use rust_xlsxwriter::{Format, Workbook, XlsxError};
+use std::collections::{BTreeMap, HashMap, HashSet};
+use std::path::Path;
+
+/// A request in both reports whose contents changed.
+#[derive(Debug)]
+pub struct Cambio<'a> {
+    pub tipo: &'a str,
+    pub nueva: &'a Solicitud,
+    /// Field, older value and newer value.
+    pub diferencias: Vec<(&'static str, String, String)>,
+}
+
+/// Differences between two agenda reports, matched by request number.
+#[derive(Debug, Default)]
+pub struct Comparacion<'a> {
+    pub nuevas: Vec<(&'a str, &'a Solicitud)>,
+    /// Requests no longer in the newer report, presumably resolved.
+    pub resueltas: Vec<(&'a str, &'a Solicitud)>,
+    pub modificadas: Vec<Cambio<'a>>,
+    /// Requests missing from the newer report whose number is in one of its
+    /// unhandled chunks, so they are likely still open.
+    pub sin_procesar: Vec<(&'a str, &'a Solicitud)>,
+    /// Requests of either report whose number is empty or repeated, which
+    /// can't be matched.
+    pub repetidas: Vec<(&'a str, &'a Solicitud)>,
+}
+
+/// How many requests ended up in each group of a `Comparacion`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Conteo {
+    pub nuevas: usize,
+    pub resueltas: usize,
+    pub modificadas: usize,
+    pub sin_procesar: usize,
+    pub repetidas: usize,
+}
+
+impl Comparacion<'_> {
+    pub fn conteo(&self) -> Conteo {
+        Conteo {
+            nuevas: self.nuevas.len(),
+            resueltas: self.resueltas.len(),
+            modificadas: self.modificadas.len(),
+            sin_procesar: self.sin_procesar.len(),
+            repetidas: self.repetidas.len(),
+        }
+    }
+}
+
+/// A request and the type of sheet it is in.
+type Fila<'a> = (&'a str, &'a Solicitud);
+
+const TIPO: &str = "Tipo de solicitud";
+const REPETIDAS: &str = "Repetidas o sin número";
+
+/// Requests of a report by request number, skipping anotaciones, and those
+/// whose number is empty or repeated, which can't be matched. A `BTreeMap`
+/// keeps the output sorted by request number.
+fn por_numero(reporte: &Reporte) -> (BTreeMap<&str, Fila<'_>>, Vec<Fila<'_>>) {
+    let mut grupos: BTreeMap<&str, Vec<Fila>> = BTreeMap::new();
+    for (tipo, solicitudes) in &reporte.solicitudes {
+        if tipo == ANOTACIONES {
+            continue;
+        }
+        for solicitud in solicitudes {
+            grupos
+                .entry(solicitud.numero_solicitud.as_str())
+                .or_default()
+                .push((tipo.as_str(), solicitud));
+        }
+    }
+    let mut unicas = BTreeMap::new();
+    let mut repetidas = Vec::new();
+    for (numero, mut grupo) in grupos {
+        if let [unica] = grupo.as_slice()
+            && !numero.trim().is_empty()
+        {
+            unicas.insert(numero, *unica);
+        } else {
+            grupo.sort_by_key(|(tipo, _)| *tipo);
+            repetidas.extend(grupo);
+        }
+    }
+    (unicas, repetidas)
+}
+
+/// Whether `numero` shows up in a chunk of `reporte` that couldn't be parsed.
+fn sin_procesar_en(reporte: &Reporte, numero: &str) -> bool {
+    reporte.sin_procesar.iter().any(|chunk| {
+        chunk
+            .texto
+            .split_whitespace()
+            .any(|palabra| palabra.trim_matches(|c: char| !c.is_alphanumeric()) == numero)
+    })
+}
+
+/// Fields worth comparing. Pages move around between agendas without the
+/// request changing, so they are left out.
+fn campos(solicitud: &Solicitud) -> HashMap<Campo, String> {
+    solicitud
+        .iter()
+        .filter(|(campo, _)| *campo != Campo::Paginas)
+        .collect()
+}
+
+pub fn comparar<'a>(anterior: &'a Reporte, nuevo: &'a Reporte) -> Comparacion<'a> {
+    let (mut anteriores, repetidas_anteriores) = por_numero(anterior);
+    let (mut nuevos, repetidas_nuevas) = por_numero(nuevo);
+    let mut comparacion = Comparacion::default();
+
+    // A number repeated in one report can't be matched in the other either.
+    let repetidos: HashSet<&str> = repetidas_anteriores
+        .iter()
+        .chain(&repetidas_nuevas)
+        .map(|(_, solicitud)| solicitud.numero_solicitud.as_str())
+        .collect();
+    for (mut repetidas, unicas) in [
+        (repetidas_anteriores, &mut anteriores),
+        (repetidas_nuevas, &mut nuevos),
+    ] {
+        repetidas.extend(repetidos.iter().filter_map(|numero| unicas.remove(numero)));
+        comparacion.repetidas.extend(repetidas);
+    }
+    // By number, the older report's first.
+    comparacion
+        .repetidas
+        .sort_by_key(|(_, solicitud)| solicitud.numero_solicitud.as_str());
+
+    for (numero, &(tipo, nueva)) in &nuevos {
+        let Some(&(tipo_anterior, vieja)) = anteriores.get(numero) else {
+            comparacion.nuevas.push((tipo, nueva));
+            continue;
+        };
+
+        let mut diferencias = Vec::new();
+        if tipo_anterior != tipo {
+            diferencias.push((
+                tr(TIPO),
+                tr(tipo_anterior).to_string(),
+                tr(tipo).to_string(),
+            ));
+        }
+        let campos_anteriores = campos(vieja);
+        let campos_nuevos = campos(nueva);
+        for campo in [
+            Campo::NombreDelEstudiante,
+            Campo::PlanDeEstudios,
+            Campo::FechaDeSolicitud,
+            Campo::Identificacion,
+            Campo::Adjuntos,
+            Campo::Materias,
+            Campo::Periodo,
+            Campo::Motivos,
+        ] {
+            let antes = campos_anteriores.get(&campo).cloned().unwrap_or_default();
+            let despues = campos_nuevos.get(&campo).cloned().unwrap_or_default();
+            if antes.trim() != despues.trim() {
+                diferencias.push((campo.encabezado(), antes, despues));
+            }
+        }
+        if !diferencias.is_empty() {
+            comparacion.modificadas.push(Cambio {
+                tipo,
+                nueva,
+                diferencias,
+            });
+        }
+    }
+
+    for (numero, &(tipo, vieja)) in &anteriores {
+        if nuevos.contains_key(numero) {
+            continue;
+        }
+        if sin_procesar_en(nuevo, numero) {
+            comparacion.sin_procesar.push((tipo, vieja));
+        } else {
+            comparacion.resueltas.push((tipo, vieja));
+        }
+    }
+
+    comparacion
+}
+
+fn write_lista(
+    workbook: &mut Workbook,
+    nombre: &str,
+    solicitudes: &[(&str, &Solicitud)],
+) -> Result<(), XlsxError> {
+    let worksheet = workbook.add_worksheet().set_name(tr(nombre))?;
+    let bold_format = Format::new().set_bold();
+
+    let headers = [
+        tr(TIPO),
+        Campo::NumeroSolicitud.encabezado(),
+        Campo::NombreDelEstudiante.encabezado(),
+        Campo::Identificacion.encabezado(),
+        Campo::PlanDeEstudios.encabezado(),
+        Campo::FechaDeSolicitud.encabezado(),
+    ];
+    for (col, header) in headers.iter().enumerate() {
+        worksheet.write_string_with_format(0, col as u16, *header, &bold_format)?;
+    }
+
+    for (row, (tipo, sol)) in solicitudes.iter().enumerate() {
+        let row = row as u32 + 1;
+        worksheet.write_string(row, 0, tr(tipo))?;
+        worksheet.write_string(row, 1, &sol.numero_solicitud)?;
+        worksheet.write_string(row, 2, &sol.nombre_del_estudiante)?;
+        worksheet.write_string(row, 3, sol.identificacion.to_string())?;
+        worksheet.write_string(row, 4, &sol.plan_de_estudios)?;
+        worksheet.write_string(
+            row,
+            5,
+            sol.fecha_de_solicitud.format("%d/%m/%Y").to_string(),
+        )?;
+    }
+
+    Ok(())
+}
+
+fn write_modificadas(workbook: &mut Workbook, cambios: &[Cambio]) -> Result<(), XlsxError> {
+    let worksheet = workbook.add_worksheet().set_name(tr("Modificadas"))?;
+    let bold_format = Format::new().set_bold();
+    let texto_format = Format::new().set_text_wrap();
+
+    let headers = [
+        tr(TIPO),
+        Campo::NumeroSolicitud.encabezado(),
+        Campo::NombreDelEstudiante.encabezado(),
+        tr("Campo"),
+        tr("Valor anterior"),
+        tr("Valor nuevo"),
+    ];
+    for (col, header) in headers.iter().enumerate() {
+        worksheet.write_string_with_format(0, col as u16, *header, &bold_format)?;
+    }
+
+    // One row per changed field
+    let mut row = 1;
+    for cambio in cambios {
+        for (campo, antes, despues) in &cambio.diferencias {
+            worksheet.write_string(row, 0, tr(cambio.tipo))?;
+            worksheet.write_string(row, 1, &cambio.nueva.numero_solicitud)?;
+            worksheet.write_string(row, 2, &cambio.nueva.nombre_del_estudiante)?;
+            worksheet.write_string(row, 3, *campo)?;
+            worksheet.write_string_with_format(row, 4, antes, &texto_format)?;
+            worksheet.write_string_with_format(row, 5, despues, &texto_format)?;
+            row += 1;
+        }
+    }
+    worksheet.set_column_width(4, 60)?;
+    worksheet.set_column_width(5, 60)?;
+
+    Ok(())
+}
+
+pub fn write_comparacion(
+    comparacion: &Comparacion,
+    procedencias: &[Procedencia],
+    excel_path: &Path,
+) -> Result<(), XlsxError> {
+    let mut workbook = Workbook::new();
+
+    write_lista(&mut workbook, "Nuevas", &comparacion.nuevas)?;
+    write_lista(&mut workbook, "Resueltas", &comparacion.resueltas)?;
+    write_modificadas(&mut workbook, &comparacion.modificadas)?;
+    if !comparacion.sin_procesar.is_empty() {
+        write_lista(&mut workbook, SIN_PROCESAR, &comparacion.sin_procesar)?;
+    }
+    if !comparacion.repetidas.is_empty() {
+        write_lista(&mut workbook, REPETIDAS, &comparacion.repetidas)?;
+    }
+    write_procedencia(&mut workbook, procedencias)?;
+
+    workbook.save(excel_path)?;
+
+    Ok(())
+}
+
+/// Compares an older and a newer report and writes the changes to
+/// `excel_path`. Returns how many requests ended up in each group.
+pub fn process_comparacion(
+    anterior: &Reporte,
+    nuevo: &Reporte,
+    excel_path: &Path,
+) -> Result<Conteo, String> {
+    let comparacion = comparar(anterior, nuevo);
+    let procedencias = [anterior.procedencias.clone(), nuevo.procedencias.clone()].concat();
+    write_comparacion(&comparacion, &procedencias, excel_path)
+        .map_err(|e| format!("Failed to write Excel file: {e}"))?;
+    Ok(comparacion.conteo())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::pdf_handling::{CEA, CS, SinProcesar};
+
+    fn solicitud(numero: &str, nombre: &str) -> Solicitud {
+        Solicitud {
+            numero_solicitud: numero.to_string(),
+            nombre_del_estudiante: nombre.to_string(),
+            ..Default::default()
+        }
+    }
+
+    fn reporte(solicitudes: Vec<(&str, Solicitud)>) -> Reporte {
+        let mut reporte = Reporte::default();
+        for (tipo, solicitud) in solicitudes {
+            reporte
+                .solicitudes
+                .entry(tipo.to_string())
+                .or_default()
+                .push(solicitud);
+        }
+        reporte
+    }
+
+    #[test]
+    fn comparar_separa_nuevas_resueltas_y_modificadas() {
+        let anterior = reporte(vec![
+            (CEA, solicitud("CEA1", "Ana")),
+            (CEA, solicitud("CEA2", "Luis")),
+            (CS, solicitud("CS1", "Marta")),
+        ]);
+        let nuevo = reporte(vec![
+            (CEA, solicitud("CEA1", "Ana")),
+            (CEA, solicitud("CEA3", "Juan")),
+            (CS, solicitud("CS1", "Marta Gómez")),
+        ]);
+
+        let comparacion = comparar(&anterior, &nuevo);
+
+        let numeros = |lista: &[(&str, &Solicitud)]| -> Vec<String> {
+            lista
+                .iter()
+                .map(|(_, s)| s.numero_solicitud.clone())
+                .collect()
+        };
+        assert_eq!(numeros(&comparacion.nuevas), vec!["CEA3"]);
+        assert_eq!(numeros(&comparacion.resueltas), vec!["CEA2"]);
+        assert_eq!(comparacion.modificadas.len(), 1);
+        let cambio = &comparacion.modificadas[0];
+        assert_eq!(cambio.nueva.numero_solicitud, "CS1");
+        assert_eq!(
+            cambio.diferencias,
+            vec![(
+                Campo::NombreDelEstudiante.encabezado(),
+                "Marta".to_string(),
+                "Marta Gómez".to_string()
+            )]
+        );
+    }
+
+    #[test]
+    fn comparar_registra_el_cambio_de_tipo_e_ignora_paginas_y_espacios() {
+        let mut vieja = solicitud("CEA1", "Ana");
+        vieja.paginas = Some((1, 1));
+        let mut nueva = solicitud("CEA1", "Ana ");
+        nueva.paginas = Some((3, 4));
+        let anterior = reporte(vec![(CEA, vieja)]);
+        let nuevo = reporte(vec![(CS, nueva)]);
+
+        let comparacion = comparar(&anterior, &nuevo);
+
+        assert!(comparacion.nuevas.is_empty() && comparacion.resueltas.is_empty());
+        assert_eq!(
+            comparacion.modificadas[0].diferencias,
+            vec![(tr(TIPO), tr(CEA).to_string(), tr(CS).to_string())]
+        );
+    }
+
+    #[test]
+    fn comparar_no_da_por_resueltas_las_que_quedaron_sin_procesar() {
+        let anterior = reporte(vec![
+            (CEA, solicitud("CEA-1", "Ana")),
+            (CEA, solicitud("CEA-2", "Luis")),
+        ]);
+        let mut nuevo = reporte(vec![]);
+        nuevo.sin_procesar.push(SinProcesar {
+            posicion: 1,
+            texto: "Número y fecha de la solicitud: CEA-1, 12/03/2024".to_string(),
+            paginas: None,
+            motivo: String::new(),
+            origen: 0,
+        });
+
+        let comparacion = comparar(&anterior, &nuevo);
+
+        assert_eq!(comparacion.sin_procesar.len(), 1);
+        assert_eq!(comparacion.sin_procesar[0].1.numero_solicitud, "CEA-1");
+        assert_eq!(comparacion.resueltas.len(), 1);
+        assert_eq!(comparacion.resueltas[0].1.numero_solicitud, "CEA-2");
+    }
+
+    #[test]
+    fn comparar_reporta_numeros_repetidos_o_vacios_sin_emparejarlos() {
+        let anterior = reporte(vec![
+            (CEA, solicitud("CEA1", "Ana")),
+            (CEA, solicitud("CEA2", "Luis")),
+        ]);
+        let nuevo = reporte(vec![
+            (CEA, solicitud("CEA1", "Ana")),
+            (CS, solicitud("CEA1", "Otra Ana")),
+            (CEA, solicitud("", "Sin número")),
+            (CEA, solicitud("CEA2", "Luis")),
+        ]);
+
+        let comparacion = comparar(&anterior, &nuevo);
+
+        let nombres: Vec<&str> = comparacion
+            .repetidas
+            .iter()
+            .map(|(_, s)| s.nombre_del_estudiante.as_str())
+            .collect();
+        assert_eq!(nombres, vec!["Sin número", "Ana", "Ana", "Otra Ana"]);
+        assert!(comparacion.nuevas.is_empty());
+        assert!(comparacion.resueltas.is_empty());
+        assert!(comparacion.modificadas.is_empty());
+    }
+
+    #[test]
+    fn comparar_no_cuenta_las_anotaciones() {
+        let anterior = reporte(vec![(ANOTACIONES, solicitud("", "antes"))]);
+        let nuevo = reporte(vec![(ANOTACIONES, solicitud("", "despues"))]);
+
+        let comparacion = comparar(&anterior, &nuevo);
+
+        assert!(comparacion.nuevas.is_empty());
+        assert!(comparacion.resueltas.is_empty());
+        assert!(comparacion.modificadas.is_empty());
+    }
+}
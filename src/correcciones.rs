@@ -0,0 +1,159 @@
+use crate::ajustes;
+use crate::pdf_handling::{ANOTACIONES, Campo, Reporte};
+use std::collections::HashMap;
+use std::path::{Path, PathBuf};
+
+/// Known fixes to misparsed fields, by request number, applied on every run.
+///
+/// ```toml
+/// ["CEA-123"]
+/// "Nombre del estudiante" = "Juan Pérez"
+/// "Identificación" = 12345678
+/// ```
+#[derive(Debug, Default)]
+pub struct Correcciones {
+    por_numero: HashMap<String, Vec<(Campo, String)>>,
+}
+
+/// Corrections file used when the user doesn't pick one.
+pub fn ruta_predeterminada() -> Option<PathBuf> {
+    ajustes::dirs().map(|dirs| dirs.config_dir().join("correcciones.toml"))
+}
+
+impl Correcciones {
+    pub fn leer(path: &Path) -> Result<Self, String> {
+        let contenido = std::fs::read_to_string(path)
+            .map_err(|e| format!("Error reading '{}': {e}", path.display()))?;
+        let tabla: toml::Table = contenido
+            .parse()
+            .map_err(|e| format!("Error parsing '{}': {e}", path.display()))?;
+
+        let mut correcciones = Correcciones::default();
+        for (numero, campos) in tabla {
+            let Some(campos) = campos.as_table() else {
+                return Err(format!(
+                    "'{numero}' in '{}' should be a table of fields",
+                    path.display()
+                ));
+            };
+            let mut lista = Vec::with_capacity(campos.len());
+            for (encabezado, valor) in campos {
+                let campo = Campo::desde_encabezado(encabezado)
+                    .filter(|c| !matches!(c, Campo::NumeroSolicitud | Campo::Paginas))
+                    .ok_or_else(|| format!("Unknown field '{encabezado}' for '{numero}'"))?;
+                let valor = match valor {
+                    toml::Value::String(texto) => texto.clone(),
+                    otro => otro.to_string(),
+                };
+                lista.push((campo, valor));
+            }
+            correcciones.por_numero.insert(numero, lista);
+        }
+        Ok(correcciones)
+    }
+
+    /// Overrides the listed fields in `reporte`. Returns how many were applied.
+    pub fn aplicar(&self, reporte: &mut Reporte) -> Result<usize, String> {
+        let mut aplicadas = 0;
+        for (tipo, solicitudes) in reporte.solicitudes.iter_mut() {
+            if tipo == ANOTACIONES {
+                continue;
+            }
+            for sol in solicitudes.iter_mut() {
+                let Some(campos) = self.por_numero.get(&sol.numero_solicitud) else {
+                    continue;
+                };
+                for (campo, valor) in campos {
+                    sol.corregir(*campo, valor).map_err(|e| {
+                        format!(
+                            "Error in correction of '{}' for '{}': {e}",
+                            campo.encabezado(),
+                            sol.numero_solicitud
+                        )
+                    })?;
+                    aplicadas += 1;
+                }
+            }
+        }
+        Ok(aplicadas)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::pdf_handling::{CEA, Solicitud};
+
+    /// Reads `contenido` as a corrections file.
+    fn leer(nombre: &str, contenido: &str) -> Result<Correcciones, String> {
+        let path =
+            std::env::temp_dir().join(format!("reps-sia-{}-{nombre}.toml", std::process::id()));
+        std::fs::write(&path, contenido).unwrap();
+        let correcciones = Correcciones::leer(&path);
+        let _ = std::fs::remove_file(&path);
+        correcciones
+    }
+
+    #[test]
+    fn leer_acepta_textos_y_numeros() {
+        let correcciones = leer(
+            "correcciones",
+            r#"
+            ["CEA-123"]
+            "Nombre del estudiante" = "Juan Pérez"
+            "Identificación" = 12345678
+            "#,
+        )
+        .unwrap();
+
+        let mut campos = correcciones.por_numero["CEA-123"].clone();
+        campos.sort_by_key(|(campo, _)| campo.encabezado());
+        assert_eq!(
+            campos,
+            vec![
+                (Campo::Identificacion, "12345678".to_string()),
+                (Campo::NombreDelEstudiante, "Juan Pérez".to_string()),
+            ]
+        );
+    }
+
+    #[test]
+    fn leer_rechaza_campos_desconocidos_o_que_no_se_corrigen() {
+        for (nombre, contenido) in [
+            ("desconocido", "[\"CEA-1\"]\n\"Color\" = \"azul\""),
+            ("numero", "[\"CEA-1\"]\n\"Número de solicitud\" = \"CEA-2\""),
+            ("paginas", "[\"CEA-1\"]\n\"Página(s)\" = \"1-2\""),
+            ("sin_tabla", "\"CEA-1\" = \"Juan\""),
+        ] {
+            assert!(leer(nombre, contenido).is_err(), "{contenido}");
+        }
+    }
+
+    #[test]
+    fn aplicar_corrige_y_recuerda_el_valor_original() {
+        let correcciones = leer(
+            "aplicar",
+            "[\"CEA-1\"]\n\"Nombre del estudiante\" = \"Juan Pérez\"",
+        )
+        .unwrap();
+        let mut reporte = Reporte::default();
+        reporte.solicitudes.insert(
+            CEA.to_string(),
+            vec![Solicitud {
+                numero_solicitud: "CEA-1".to_string(),
+                nombre_del_estudiante: "Juan Perez".to_string(),
+                ..Default::default()
+            }],
+        );
+
+        assert_eq!(correcciones.aplicar(&mut reporte), Ok(1));
+        let sol = &reporte.solicitudes[CEA][0];
+        assert_eq!(sol.nombre_del_estudiante, "Juan Pérez");
+        assert_eq!(
+            sol.originales
+                .get(&Campo::NombreDelEstudiante)
+                .map(String::as_str),
+            Some("Juan Perez")
+        );
+    }
+}
@@ -0,0 +1,300 @@
+use crate::ajustes;
+use crate::pdf_handling::{ANOTACIONES, Reporte};
+use chrono::Local;
+use rusqlite::{Connection, params};
+use std::path::{Path, PathBuf};
+
+/// Local database of every processed request, to look up a student across agendas.
+pub struct Historial {
+    conn: Connection,
+}
+
+/// A request as it was the last time it appeared in a report.
+#[derive(Debug, Clone)]
+pub struct Registro {
+    pub numero_solicitud: String,
+    pub tipo: String,
+    pub nombre_del_estudiante: String,
+    pub identificacion: String,
+    pub plan_de_estudios: String,
+    pub fecha_de_solicitud: String,
+    pub motivos: Option<String>,
+    /// Files of the reports it appeared in, comma separated.
+    pub reportes: String,
+}
+
+const ESQUEMA: &str = "
+CREATE TABLE IF NOT EXISTS reportes (
+    id INTEGER PRIMARY KEY,
+    archivo TEXT NOT NULL,
+    sha256 TEXT NOT NULL UNIQUE,
+    procesado TEXT NOT NULL
+);
+CREATE TABLE IF NOT EXISTS solicitudes (
+    numero_solicitud TEXT PRIMARY KEY,
+    tipo TEXT NOT NULL,
+    nombre_del_estudiante TEXT NOT NULL,
+    identificacion TEXT NOT NULL,
+    plan_de_estudios TEXT NOT NULL,
+    fecha_de_solicitud TEXT NOT NULL,
+    adjuntos INTEGER,
+    materias TEXT,
+    periodo TEXT,
+    motivos TEXT,
+    actualizada TEXT NOT NULL
+);
+CREATE TABLE IF NOT EXISTS apariciones (
+    numero_solicitud TEXT NOT NULL REFERENCES solicitudes(numero_solicitud),
+    reporte_id INTEGER NOT NULL REFERENCES reportes(id),
+    paginas TEXT,
+    PRIMARY KEY (numero_solicitud, reporte_id)
+);
+CREATE INDEX IF NOT EXISTS solicitudes_identificacion ON solicitudes(identificacion);
+CREATE INDEX IF NOT EXISTS solicitudes_nombre ON solicitudes(nombre_del_estudiante);
+";
+
+/// Where the history lives unless the user says otherwise.
+pub fn ruta_predeterminada() -> Option<PathBuf> {
+    ajustes::dirs().map(|dirs| dirs.data_dir().join("historial.sqlite3"))
+}
+
+impl Historial {
+    pub fn abrir(path: &Path) -> Result<Self, String> {
+        if let Some(parent) = path.parent() {
+            std::fs::create_dir_all(parent)
+                .map_err(|e| format!("Error creating '{}': {e}", parent.display()))?;
+        }
+        let conn = Connection::open(path)
+            .map_err(|e| format!("Error opening history '{}': {e}", path.display()))?;
+        Self::con(conn)
+    }
+
+    fn con(conn: Connection) -> Result<Self, String> {
+        conn.execute_batch(ESQUEMA)
+            .map_err(|e| format!("Error creating history tables: {e}"))?;
+        Ok(Self { conn })
+    }
+
+    pub fn abrir_predeterminado() -> Result<Self, String> {
+        let path = ruta_predeterminada().ok_or("No user data directory available")?;
+        Self::abrir(&path)
+    }
+
+    /// Upserts every report and request in `reporte`. Returns how many requests were saved.
+    pub fn registrar(&mut self, reporte: &Reporte) -> Result<usize, String> {
+        self.registrar_inner(reporte)
+            .map_err(|e| format!("Error saving to history: {e}"))
+    }
+
+    fn registrar_inner(&mut self, reporte: &Reporte) -> rusqlite::Result<usize> {
+        let tx = self.conn.transaction()?;
+        let ahora = Local::now().to_rfc3339();
+
+        let mut reporte_ids = Vec::with_capacity(reporte.procedencias.len());
+        for procedencia in &reporte.procedencias {
+            let id: i64 = tx.query_row(
+                "INSERT INTO reportes (archivo, sha256, procesado) VALUES (?1, ?2, ?3)
+                 ON CONFLICT(sha256) DO UPDATE SET archivo = excluded.archivo, procesado = excluded.procesado
+                 RETURNING id",
+                params![
+                    procedencia.archivo(),
+                    procedencia.sha256,
+                    procedencia.procesado.to_rfc3339()
+                ],
+                |row| row.get(0),
+            )?;
+            reporte_ids.push(id);
+        }
+
+        let mut guardadas = 0;
+        for (tipo, solicitudes) in &reporte.solicitudes {
+            if tipo == ANOTACIONES {
+                continue;
+            }
+            for sol in solicitudes {
+                if sol.numero_solicitud.is_empty() {
+                    continue;
+                }
+                tx.execute(
+                    "INSERT INTO solicitudes (numero_solicitud, tipo, nombre_del_estudiante,
+                        identificacion, plan_de_estudios, fecha_de_solicitud, adjuntos, materias,
+                        periodo, motivos, actualizada)
+                     VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7, ?8, ?9, ?10, ?11)
+                     ON CONFLICT(numero_solicitud) DO UPDATE SET
+                        tipo = excluded.tipo,
+                        nombre_del_estudiante = excluded.nombre_del_estudiante,
+                        identificacion = excluded.identificacion,
+                        plan_de_estudios = excluded.plan_de_estudios,
+                        fecha_de_solicitud = excluded.fecha_de_solicitud,
+                        adjuntos = excluded.adjuntos,
+                        materias = excluded.materias,
+                        periodo = excluded.periodo,
+                        motivos = excluded.motivos,
+                        actualizada = excluded.actualizada",
+                    params![
+                        sol.numero_solicitud,
+                        tipo,
+                        sol.nombre_del_estudiante,
+                        sol.identificacion.to_string(),
+                        sol.plan_de_estudios,
+                        sol.fecha_de_solicitud.format("%Y-%m-%d").to_string(),
+                        sol.adjuntos.map(|a| a as i64),
+                        sol.materias,
+                        sol.periodo,
+                        sol.motivos,
+                        ahora,
+                    ],
+                )?;
+                let paginas = sol.paginas.map(|(primera, ultima)| {
+                    if primera == ultima {
+                        primera.to_string()
+                    } else {
+                        format!("{primera}-{ultima}")
+                    }
+                });
+                for &origen in &sol.origenes {
+                    let Some(reporte_id) = reporte_ids.get(origen) else {
+                        continue;
+                    };
+                    tx.execute(
+                        "INSERT INTO apariciones (numero_solicitud, reporte_id, paginas)
+                         VALUES (?1, ?2, ?3)
+                         ON CONFLICT(numero_solicitud, reporte_id) DO UPDATE SET paginas = excluded.paginas",
+                        params![sol.numero_solicitud, reporte_id, paginas],
+                    )?;
+                }
+                guardadas += 1;
+            }
+        }
+
+        tx.commit()?;
+        Ok(guardadas)
+    }
+
+    /// Requests whose identification is `consulta` or whose student name or
+    /// request number contains it, newest first.
+    pub fn buscar(&self, consulta: &str) -> Result<Vec<Registro>, String> {
+        self.buscar_inner(consulta.trim())
+            .map_err(|e| format!("Error searching history: {e}"))
+    }
+
+    fn buscar_inner(&self, consulta: &str) -> rusqlite::Result<Vec<Registro>> {
+        // `%` and `_` typed by the user are searched for, not wildcards.
+        let patron = consulta
+            .replace('\\', "\\\\")
+            .replace('%', "\\%")
+            .replace('_', "\\_");
+        let mut stmt = self.conn.prepare(
+            "SELECT s.numero_solicitud, s.tipo, s.nombre_del_estudiante, s.identificacion,
+                    s.plan_de_estudios, s.fecha_de_solicitud, s.motivos,
+                    COALESCE(GROUP_CONCAT(r.archivo, ', '), '')
+             FROM solicitudes s
+             LEFT JOIN apariciones a ON a.numero_solicitud = s.numero_solicitud
+             LEFT JOIN reportes r ON r.id = a.reporte_id
+             WHERE s.identificacion = ?1
+                OR s.nombre_del_estudiante LIKE '%' || ?2 || '%' ESCAPE '\\'
+                OR s.numero_solicitud LIKE '%' || ?2 || '%' ESCAPE '\\'
+             GROUP BY s.numero_solicitud
+             ORDER BY s.fecha_de_solicitud DESC, s.numero_solicitud",
+        )?;
+        let registros = stmt
+            .query_map(params![consulta, patron], |row| {
+                let fecha: String = row.get(5)?;
+                Ok(Registro {
+                    numero_solicitud: row.get(0)?,
+                    tipo: row.get(1)?,
+                    nombre_del_estudiante: row.get(2)?,
+                    identificacion: row.get(3)?,
+                    plan_de_estudios: row.get(4)?,
+                    fecha_de_solicitud: chrono::NaiveDate::parse_from_str(&fecha, "%Y-%m-%d")
+                        .map(|d| d.format("%d/%m/%Y").to_string())
+                        .unwrap_or(fecha),
+                    motivos: row.get(6)?,
+                    reportes: row.get(7)?,
+                })
+            })?
+            .collect::<rusqlite::Result<Vec<Registro>>>()?;
+        Ok(registros)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::pdf_handling::{CEA, Procedencia, Solicitud};
+
+    fn historial() -> Historial {
+        Historial::con(Connection::open_in_memory().unwrap()).unwrap()
+    }
+
+    fn reporte(pdf: &str, nombre: &str) -> Reporte {
+        let mut reporte = Reporte {
+            procedencias: vec![Procedencia::new(Path::new(pdf), pdf.as_bytes())],
+            ..Default::default()
+        };
+        reporte.solicitudes.insert(
+            CEA.to_string(),
+            vec![Solicitud {
+                numero_solicitud: "CEA-123".to_string(),
+                nombre_del_estudiante: nombre.to_string(),
+                identificacion: 1037654321,
+                origenes: vec![0],
+                ..Default::default()
+            }],
+        );
+        reporte
+    }
+
+    fn filas(historial: &Historial, tabla: &str) -> i64 {
+        historial
+            .conn
+            .query_row(&format!("SELECT COUNT(*) FROM {tabla}"), [], |row| {
+                row.get(0)
+            })
+            .unwrap()
+    }
+
+    #[test]
+    fn registrar_actualiza_la_solicitud_y_suma_apariciones() {
+        let mut historial = historial();
+
+        historial
+            .registrar(&reporte("agenda1.pdf", "Ana Perez"))
+            .unwrap();
+        historial
+            .registrar(&reporte("agenda2.pdf", "Ana Pérez"))
+            .unwrap();
+
+        assert_eq!(filas(&historial, "solicitudes"), 1);
+        assert_eq!(filas(&historial, "apariciones"), 2);
+        let registros = historial.buscar("CEA-123").unwrap();
+        assert_eq!(registros.len(), 1);
+        assert_eq!(registros[0].nombre_del_estudiante, "Ana Pérez");
+        assert_eq!(registros[0].reportes, "agenda1.pdf, agenda2.pdf");
+    }
+
+    #[test]
+    fn buscar_por_identificacion_nombre_o_numero() {
+        let mut historial = historial();
+        historial
+            .registrar(&reporte("agenda.pdf", "Ana Pérez"))
+            .unwrap();
+
+        for consulta in ["1037654321", "Pérez", " ana ", "EA-12"] {
+            assert_eq!(historial.buscar(consulta).unwrap().len(), 1, "{consulta}");
+        }
+        assert!(historial.buscar("103765").unwrap().is_empty());
+    }
+
+    #[test]
+    fn buscar_no_usa_comodines_de_la_consulta() {
+        let mut historial = historial();
+        historial
+            .registrar(&reporte("agenda.pdf", "Ana Pérez"))
+            .unwrap();
+
+        for consulta in ["%", "_", "Ana%Pérez", "CEA_123"] {
+            assert!(historial.buscar(consulta).unwrap().is_empty(), "{consulta}");
+        }
+    }
+}
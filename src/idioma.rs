@@ -0,0 +1,377 @@
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+use std::fmt::Display;
+use std::sync::LazyLock;
+use std::sync::atomic::{AtomicU8, Ordering};
+
+/// Language of the window and of the workbooks written.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, Serialize, Deserialize, clap::ValueEnum)]
+pub enum Idioma {
+    #[default]
+    Es,
+    En,
+}
+
+impl Idioma {
+    pub const TODOS: [Idioma; 2] = [Idioma::Es, Idioma::En];
+
+    /// Name of the language in itself, so it can be found whatever is selected.
+    pub fn nombre(self) -> &'static str {
+        match self {
+            Idioma::Es => "Español",
+            Idioma::En => "English",
+        }
+    }
+}
+
+static ACTUAL: AtomicU8 = AtomicU8::new(0);
+
+pub fn actual() -> Idioma {
+    match ACTUAL.load(Ordering::Relaxed) {
+        1 => Idioma::En,
+        _ => Idioma::Es,
+    }
+}
+
+/// Makes `idioma` the one used from now on, also by workbooks being written.
+pub fn usar(idioma: Idioma) {
+    ACTUAL.store(idioma as u8, Ordering::Relaxed);
+}
+
+static EN: LazyLock<HashMap<&'static str, &'static str>> =
+    LazyLock::new(|| CATALOGO_EN.iter().copied().collect());
+
+fn en(texto: &str) -> &str {
+    EN.get(texto).copied().unwrap_or(texto)
+}
+
+/// `texto` in the current language. The Spanish text is the key, and it is
+/// also what shows when a translation is missing.
+pub fn tr(texto: &str) -> &str {
+    match actual() {
+        Idioma::Es => texto,
+        Idioma::En => en(texto),
+    }
+}
+
+/// Like `tr`, filling each `{}` with the next of `args`.
+pub fn trf(texto: &str, args: &[&dyn Display]) -> String {
+    let mut resultado = String::new();
+    for (i, parte) in tr(texto).split("{}").enumerate() {
+        if i > 0
+            && let Some(arg) = args.get(i - 1)
+        {
+            resultado += &arg.to_string();
+        }
+        resultado += parte;
+    }
+    resultado
+}
+
+/// `texto` in every language, to recognise headers of workbooks written in
+/// any of them.
+pub fn todas(texto: &'static str) -> [&'static str; 2] {
+    [texto, en(texto)]
+}
+
+/// Spanish text and its English translation.
+const CATALOGO_EN: &[(&str, &str)] = &[
+    // Sheets and headers of the workbooks.
+    ("CANCELACIÓN EXTEMP. ASIGNATURAS", "LATE COURSE WITHDRAWAL"),
+    ("CANCELACIÓN SEMESTRE", "SEMESTER WITHDRAWAL"),
+    ("AUTORIZACIÓN CARGA MÍNIMA", "MINIMUM LOAD AUTHORIZATION"),
+    ("REGISTRO TRABAJO GRADO", "THESIS REGISTRATION"),
+    ("ANOTACIONES", "ANNOTATIONS"),
+    ("Sin procesar", "Unhandled"),
+    ("Metadatos", "Metadata"),
+    ("Leyenda", "Legend"),
+    ("Originales", "Originals"),
+    ("Conflictos", "Conflicts"),
+    ("Nuevas", "New"),
+    ("Resueltas", "Resolved"),
+    ("Modificadas", "Modified"),
+    ("Repetidas o sin número", "Repeated or unnumbered"),
+    ("Nombre del estudiante", "Student name"),
+    ("Plan de estudios", "Study plan"),
+    ("Número de solicitud", "Request number"),
+    ("Fecha de solicitud", "Request date"),
+    ("Identificación", "ID number"),
+    ("Adjuntos", "Attachments"),
+    ("Materias", "Courses"),
+    ("Periodo", "Term"),
+    ("Motivos", "Reasons"),
+    ("Página(s)", "Page(s)"),
+    ("Reporte de origen", "Source report"),
+    ("Decisión", "Decision"),
+    ("Notas", "Notes"),
+    ("Posición", "Position"),
+    ("Motivo", "Reason"),
+    ("Texto", "Text"),
+    ("Tipo de solicitud", "Request type"),
+    ("Campo", "Field"),
+    ("Valor", "Value"),
+    ("Valor anterior", "Previous value"),
+    ("Valor nuevo", "New value"),
+    ("Valor del revisor", "Reviewer value"),
+    ("Valor anterior del PDF", "Previous PDF value"),
+    ("Valor nuevo del PDF", "New PDF value"),
+    ("Valor en el PDF: {}", "Value in the PDF: {}"),
+    ("Archivo fuente", "Source file"),
+    ("SHA-256 del PDF", "PDF SHA-256"),
+    ("Versión de reps-sia", "reps-sia version"),
+    ("Versión de reglas", "Rules version"),
+    ("Procesado", "Processed"),
+    ("Reporte de agenda {}", "Agenda report {}"),
+    (
+        "Reporte de agenda consolidado",
+        "Consolidated agenda report",
+    ),
+    ("Generado a partir de {}", "Generated from {}"),
+    ("Significado", "Meaning"),
+    ("Falta", "Missing"),
+    ("Campo obligatorio vacío", "Required field is empty"),
+    ("Sospechoso", "Suspicious"),
+    (
+        "Campo demasiado largo o con texto de otra sección del reporte, verificar contra el PDF",
+        "Field too long or with text from another section of the report, check it against the PDF",
+    ),
+    ("Nota", "Note"),
+    (
+        "El número de solicitud tiene una nota con el texto original del reporte",
+        "The request number has a note with the original text of the report",
+    ),
+    (
+        "Fragmentos del reporte que no se pudieron procesar, con el motivo y los datos rescatables",
+        "Parts of the report that could not be handled, with the reason and whatever data could be salvaged",
+    ),
+    ("Abrir", "Open"),
+    (
+        "Abrir el PDF, está en la página {}",
+        "Open the PDF, it is on page {}",
+    ),
+    (
+        "Número de solicitud '{}' no corresponde a una cancelación de asignaturas",
+        "Request number '{}' is not a course withdrawal",
+    ),
+    (
+        "Número de solicitud '{}' no corresponde a una cancelación de semestre",
+        "Request number '{}' is not a semester withdrawal",
+    ),
+    (
+        "Número de solicitud '{}' no corresponde a una autorización de carga mínima",
+        "Request number '{}' is not a minimum load authorization",
+    ),
+    (
+        "Número de solicitud '{}' no corresponde a un registro de trabajo de grado",
+        "Request number '{}' is not a thesis registration",
+    ),
+    (
+        "Ningún formato de solicitud conocido coincide con el texto",
+        "No known request format matches the text",
+    ),
+    ("Corregido", "Corrected"),
+    (
+        "Valor corregido por un revisor o por el archivo de correcciones, la nota tiene el valor del PDF",
+        "Value corrected by a reviewer or by the corrections file, the note has the PDF value",
+    ),
+    // Window.
+    (
+        "Procesador de Reportes de Agenda del SIA",
+        "SIA Agenda Report Processor",
+    ),
+    ("Procesar", "Process"),
+    ("Comparar", "Compare"),
+    ("Historial", "History"),
+    ("Reglas", "Rules"),
+    ("Revisar ({})", "Review ({})"),
+    ("Tema:", "Theme:"),
+    ("Idioma:", "Language:"),
+    ("Del sistema", "System"),
+    ("Claro", "Light"),
+    ("Oscuro", "Dark"),
+    ("Seleccione un archivo de PDF", "Select a PDF file"),
+    ("Seleccione PDF(s)", "Select PDF(s)"),
+    ("PDF o texto", "PDF or text"),
+    ("Recientes", "Recent"),
+    (
+        "Arrastre aquí PDFs o carpetas, o selecciónelos",
+        "Drop PDFs or folders here, or select them",
+    ),
+    ("{} archivos en cola", "{} files queued"),
+    ("Vaciar", "Clear"),
+    ("Quitar", "Remove"),
+    (
+        "Suelte los PDFs o carpetas para agregarlos a la cola",
+        "Drop the PDFs or folders to queue them",
+    ),
+    (
+        "Guardar también las solicitudes sin procesar en un archivo txt",
+        "Also save the unhandled requests to a txt file",
+    ),
+    (
+        "Guardar una traza de depuración junto a cada PDF (para reportar errores)",
+        "Save a debug trace next to each PDF (to report bugs)",
+    ),
+    (
+        "Consolidar todos los PDF en un solo excel",
+        "Consolidate every PDF into a single workbook",
+    ),
+    (
+        "Revisar y corregir las solicitudes antes de guardar el excel",
+        "Review and correct the requests before saving the workbook",
+    ),
+    (
+        "Libro revisado anterior (opcional)",
+        "Previous reviewed workbook (optional)",
+    ),
+    ("Archivo de correcciones", "Corrections file"),
+    ("{} (predeterminado)", "{} (default)"),
+    ("Carpeta de salida", "Output folder"),
+    ("Junto a cada reporte", "Next to each report"),
+    ("Nombre del excel:", "Workbook name:"),
+    ("Si ya existe:", "If it exists:"),
+    ("Agregar un número", "Add a number"),
+    ("Sobrescribir", "Overwrite"),
+    (
+        "Los excel que ya existan con el mismo nombre se reemplazarán. ¿Continuar?",
+        "Existing workbooks with the same name will be replaced. Continue?",
+    ),
+    ("Procesar PDF", "Process PDF"),
+    (
+        "Procesando {} (archivo {} de {})",
+        "Processing {} (file {} of {})",
+    ),
+    ("Cancelando...", "Cancelling..."),
+    ("Cancelar", "Cancel"),
+    (
+        "{} de {} procesados\n\n Por favor valida que el excel no contenga errores, este software aún es experimental.\n\nEn caso de haber solicitudes sin manejar, quedan en la hoja \"Sin procesar\" del excel\n\t-JAVM",
+        "{} of {} processed\n\n Please check the workbook for errors, this software is still experimental.\n\nRequests that could not be handled are in the \"Unhandled\" sheet of the workbook\n\t-JAVM",
+    ),
+    (
+        "{} reportes listos para revisar, el excel se escribe al exportar",
+        "{} reports ready for review, the workbook is written on export",
+    ),
+    ("\n\nProcesamiento cancelado", "\n\nProcessing cancelled"),
+    (
+        "\n\nNo se pudo guardar en el historial: {}",
+        "\n\nCould not save to the history: {}",
+    ),
+    ("{} de {} exportados", "{} of {} exported"),
+    (
+        "Revisión descartada, no se escribió ningún excel",
+        "Review discarded, no workbook was written",
+    ),
+    ("Archivo", "File"),
+    ("Estado", "Status"),
+    ("Listo", "Done"),
+    ("Error", "Error"),
+    ("Abrir", "Open"),
+    (
+        "Error: no se pudo abrir '{}': {}",
+        "Error: could not open '{}': {}",
+    ),
+    ("Reporte anterior", "Previous report"),
+    ("Reporte nuevo", "New report"),
+    ("Comparar reportes", "Compare reports"),
+    ("Comparación cancelada", "Comparison cancelled"),
+    (
+        "Espere a que termine el procesamiento para comparar",
+        "Wait for the processing to finish to compare",
+    ),
+    (
+        "Comparación lista: {} solicitudes nuevas, {} resueltas, {} modificadas, {} sin procesar en el reporte nuevo y {} con número repetido o vacío",
+        "Comparison ready: {} new requests, {} resolved, {} modified, {} unhandled in the new report and {} with a repeated or empty number",
+    ),
+    (
+        "Identificación, nombre o número de solicitud:",
+        "ID number, name or request number:",
+    ),
+    ("Buscar", "Search"),
+    ("{} solicitudes encontradas", "{} requests found"),
+    ("Tipo", "Type"),
+    ("Reportes", "Reports"),
+    ("Mostrar en la carpeta", "Show in folder"),
+    ("Al terminar:", "When done:"),
+    ("Nada", "Nothing"),
+    ("Abrir el excel", "Open the workbook"),
+    ("Vigilar carpeta", "Watch folder"),
+    ("Elegir carpeta", "Choose folder"),
+    (
+        "Vigilando '{}', cada PDF nuevo se procesa solo",
+        "Watching '{}', each new PDF is processed on its own",
+    ),
+    (
+        "Procesado {} de la carpeta vigilada",
+        "Processed {} from the watched folder",
+    ),
+    // Review.
+    (
+        "No hay reportes por revisar",
+        "There are no reports to review",
+    ),
+    ("Excel:", "Workbook:"),
+    ("Falta el campo '{}'", "The field '{}' is missing"),
+    (
+        "Las páginas no se pueden corregir",
+        "The pages can't be corrected",
+    ),
+    (
+        "El reporte no tiene solicitudes",
+        "The report has no requests",
+    ),
+    ("Sin procesar ({})", "Unhandled ({})"),
+    ("Acciones", "Actions"),
+    ("Mover a", "Move to"),
+    ("Excluir", "Exclude"),
+    ("Exportar", "Export"),
+    ("Descartar", "Discard"),
+    (
+        "Elija una solicitud que no se pudo leer para completarla a mano:",
+        "Pick a request that could not be read to complete it by hand:",
+    ),
+    (" (pág. {})", " (p. {})"),
+    (" (págs. {}-{})", " (pp. {}-{})"),
+    ("Tipo de solicitud:", "Request type:"),
+    ("Agregar a {}", "Add to {}"),
+    ("Solicitud {} agregada a {}", "Request {} added to {}"),
+    // Rules.
+    (
+        "Regla (expresión regular, cada grupo es un campo):",
+        "Rule (regular expression, each group is a field):",
+    ),
+    ("Guardar regla", "Save rule"),
+    ("Regla {} guardada", "Rule {} saved"),
+    ("Restaurar predeterminada", "Restore default"),
+    ("Fragmento del reporte:", "Report fragment:"),
+    ("Cargar de un reporte", "Load from a report"),
+    ("{} fragmentos cargados", "{} fragments loaded"),
+    ("Elegir fragmento", "Pick a fragment"),
+    ("Sin procesar {}", "Unhandled {}"),
+    ("{} coincide", "{} matches"),
+    ("{} no coincide", "{} does not match"),
+    ("Grupo {}", "Group {}"),
+    ("<sin captura>", "<no capture>"),
+    ("Fila en la hoja \"{}\"", "Row in the \"{}\" sheet"),
+    ("Queda sin procesar: {}", "Left unhandled: {}"),
+    // Search.
+    (
+        "Nombre, identificación, número de solicitud, plan o motivos:",
+        "Name, ID number, request number, plan or reasons:",
+    ),
+    (
+        "Procese reportes para buscar entre sus solicitudes",
+        "Process reports to search their requests",
+    ),
+    (
+        "{} solicitudes encontradas en {} reportes",
+        "{} requests found in {} reports",
+    ),
+    ("Solicitud {} ({})", "Request {} ({})"),
+    ("Texto del reporte", "Report text"),
+    // Log.
+    ("Registro ({} advertencias)", "Log ({} warnings)"),
+    ("{} ({} advertencias)", "{} ({} warnings)"),
+    ("General ({} advertencias)", "General ({} warnings)"),
+    ("Limpiar", "Clear"),
+    ("Abrir archivo de registro", "Open log file"),
+];
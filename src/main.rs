@@ -1,12 +1,41 @@
-//use clap::Parser;
+use ajustes::{Ajustes, Tema};
+use busqueda::Busqueda;
+use clap::Parser;
+use cola::Cola;
 use eframe::egui;
 use eframe::egui::IconData;
+use historial::{Historial, Registro};
+use idioma::{Idioma, tr, trf};
 use image::ImageReader;
-use rfd::FileDialog;
+use pdf_handling::{Opciones, Reporte};
+use resultados::{AlTerminar, Resultados};
+use rfd::{FileDialog, MessageButtons, MessageDialog, MessageDialogResult};
+use salida::{CAMPOS_PLANTILLA, Colision, Destino};
 use std::io::Cursor;
 use std::path::PathBuf;
+use std::time::Duration;
+use trabajo::{Evento, Tarea, Trabajo};
+use vigilancia::{Vigilancia, Vigilante};
+use vista_previa::{Accion, Pendiente, VistaPrevia};
+mod ajustes;
+mod bitacora;
+mod busqueda;
+mod cli;
+mod cola;
+mod comparacion;
+mod correcciones;
+mod historial;
+mod idioma;
 mod pdf_handling;
-
+mod probador;
+mod reglas;
+mod resultados;
+mod revisiones;
+mod salida;
+mod trabajo;
+mod traza;
+mod vigilancia;
+mod vista_previa;
 
 fn load_icon() -> Option<IconData> {
     let icon_bytes = include_bytes!("favicon.png"); // Replace with your favicon file
@@ -24,57 +53,763 @@ fn load_icon() -> Option<IconData> {
     })
 }
 
-/*
-#[derive(Parser, Debug)]
-#[command(author = "Jorge A. VM", about = "PDF to Text Converter")]
-struct Cli {
-    #[arg(value_name = "PDF_PATH")]
-    pdf_path: PathBuf,
-    
-    #[arg(short, long, default_value = ".", value_name = "OUTPUT_DIR")]
-    output_dir: PathBuf,
+#[derive(PartialEq)]
+enum Vista {
+    Procesar,
+    Comparar,
+    Historial,
+    Reglas,
+    Revisar,
+    Buscar,
 }
-*/
 
 struct PdfProcessorApp {
-    pdf_path: Option<Vec<PathBuf>>,
+    cola: Cola,
+    carpeta_entrada: Option<PathBuf>,
+    recientes: Vec<PathBuf>,
+    tema: Tema,
+    idioma: Idioma,
     status: String,
+    opciones: Opciones,
+    consolidar: bool,
+    revisar: bool,
+    destino: Destino,
+    al_terminar: AlTerminar,
+    vista: Vista,
+    reporte_anterior: Option<PathBuf>,
+    reporte_nuevo: Option<PathBuf>,
+    historial: Option<Historial>,
+    consulta_historial: String,
+    resultados_historial: Vec<Registro>,
+    probador: probador::Probador,
+    trabajo: Option<Trabajo>,
+    resultados: Resultados,
+    vista_previa: VistaPrevia,
+    busqueda: Busqueda,
+    carpeta_vigilada: Option<PathBuf>,
+    vigilante: Option<Vigilante>,
 }
 
-impl Default for PdfProcessorApp {
-    fn default() -> Self {
+impl PdfProcessorApp {
+    fn new(ajustes: Ajustes) -> Self {
         Self {
-            pdf_path: None,
-            status: "Seleccione un archivo de PDF".to_string(),
+            cola: Cola::default(),
+            carpeta_entrada: ajustes.carpeta_entrada,
+            recientes: ajustes.recientes,
+            tema: ajustes.tema,
+            idioma: ajustes.idioma,
+            status: tr("Seleccione un archivo de PDF").to_string(),
+            opciones: Opciones {
+                escribir_txt: ajustes.escribir_txt,
+                libro_revisado: None,
+                correcciones: ajustes.correcciones,
+                traza: ajustes.traza,
+            },
+            consolidar: ajustes.consolidar,
+            revisar: ajustes.revisar,
+            destino: Destino {
+                carpeta: ajustes.carpeta_salida,
+                plantilla: ajustes.plantilla,
+                colision: ajustes.colision,
+            },
+            al_terminar: ajustes.al_terminar,
+            vista: Vista::Procesar,
+            reporte_anterior: None,
+            reporte_nuevo: None,
+            historial: None,
+            consulta_historial: String::new(),
+            resultados_historial: Vec::new(),
+            probador: probador::Probador::new(ajustes.regla),
+            trabajo: None,
+            resultados: Resultados::default(),
+            vista_previa: VistaPrevia::default(),
+            busqueda: Busqueda::default(),
+            carpeta_vigilada: ajustes.carpeta_vigilada,
+            vigilante: None,
+        }
+    }
+
+    /// What is remembered for the next session. The reviewed book is left
+    /// out, it belongs to one run.
+    fn ajustes(&self) -> Ajustes {
+        Ajustes {
+            carpeta_entrada: self.carpeta_entrada.clone(),
+            carpeta_salida: self.destino.carpeta.clone(),
+            plantilla: self.destino.plantilla.clone(),
+            colision: self.destino.colision,
+            al_terminar: self.al_terminar,
+            escribir_txt: self.opciones.escribir_txt,
+            traza: self.opciones.traza,
+            consolidar: self.consolidar,
+            revisar: self.revisar,
+            correcciones: self.opciones.correcciones.clone(),
+            regla: self.probador.regla(),
+            tema: self.tema,
+            idioma: self.idioma,
+            recientes: self.recientes.clone(),
+            carpeta_vigilada: self.carpeta_vigilada.clone(),
         }
     }
 }
-impl eframe::App for PdfProcessorApp {
-    fn update(&mut self, ctx: &egui::Context, _frame: &mut eframe::Frame) {
-        egui::CentralPanel::default().show(ctx, |ui| {
-            ui.heading("Procesador de Reportes de Agenda del SIA");
 
-            if ui.button("Seleccione PDF(s)").clicked() {
-                if let Some(path) = FileDialog::new().add_filter("PDF", &["pdf"]).pick_files() {
-                    self.pdf_path = Some(path);
+/// File dialog opening at `carpeta`, when there is one.
+fn dialogo_en(carpeta: Option<&PathBuf>) -> FileDialog {
+    match carpeta {
+        Some(carpeta) => FileDialog::new().set_directory(carpeta),
+        None => FileDialog::new(),
+    }
+}
+
+fn seleccionar_pdf() -> Option<PathBuf> {
+    FileDialog::new()
+        .add_filter(tr("PDF o texto"), &["pdf", "txt"])
+        .pick_file()
+}
+
+impl PdfProcessorApp {
+    fn historial(&mut self) -> Result<&mut Historial, String> {
+        if self.historial.is_none() {
+            self.historial = Some(Historial::abrir_predeterminado()?);
+        }
+        Ok(self.historial.as_mut().expect("just opened"))
+    }
+
+    /// Saves a processed report in the history, returning a note for the status.
+    fn registrar(&mut self, reporte: &Reporte) -> String {
+        match self.historial().and_then(|h| h.registrar(reporte)) {
+            Ok(_) => String::new(),
+            Err(e) => trf("\n\nNo se pudo guardar en el historial: {}", &[&e]),
+        }
+    }
+
+    fn vista_procesar(&mut self, ui: &mut egui::Ui) {
+        ui.horizontal(|ui| {
+            if ui.button(tr("Seleccione PDF(s)")).clicked()
+                && let Some(paths) = dialogo_en(self.carpeta_entrada.as_ref())
+                    .add_filter(tr("PDF o texto"), &["pdf", "txt"])
+                    .pick_files()
+            {
+                self.carpeta_entrada = paths
+                    .first()
+                    .and_then(|path| path.parent())
+                    .map(PathBuf::from);
+                self.encolar(paths);
+            }
+            self.ui_recientes(ui);
+        });
+
+        self.cola.ui(ui);
+
+        ui.checkbox(
+            &mut self.opciones.escribir_txt,
+            tr("Guardar también las solicitudes sin procesar en un archivo txt"),
+        );
+
+        ui.checkbox(
+            &mut self.opciones.traza,
+            tr("Guardar una traza de depuración junto a cada PDF (para reportar errores)"),
+        );
+
+        ui.checkbox(
+            &mut self.consolidar,
+            tr("Consolidar todos los PDF en un solo excel"),
+        );
+
+        ui.checkbox(
+            &mut self.revisar,
+            tr("Revisar y corregir las solicitudes antes de guardar el excel"),
+        );
+
+        ui.horizontal(|ui| {
+            if ui
+                .button(tr("Libro revisado anterior (opcional)"))
+                .clicked()
+                && let Some(path) = FileDialog::new().add_filter("Excel", &["xlsx"]).pick_file()
+            {
+                self.opciones.libro_revisado = Some(path);
+            }
+            if let Some(path) = &self.opciones.libro_revisado {
+                ui.label(path.display().to_string());
+                if ui.button(tr("Quitar")).clicked() {
+                    self.opciones.libro_revisado = None;
+                }
+            }
+        });
+
+        ui.horizontal(|ui| {
+            if ui.button(tr("Archivo de correcciones")).clicked()
+                && let Some(path) = FileDialog::new().add_filter("TOML", &["toml"]).pick_file()
+            {
+                self.opciones.correcciones = Some(path);
+            }
+            if let Some(path) = &self.opciones.correcciones {
+                ui.label(path.display().to_string());
+                if ui.button(tr("Quitar")).clicked() {
+                    self.opciones.correcciones = None;
+                }
+            } else if let Some(path) =
+                correcciones::ruta_predeterminada().filter(|path| path.exists())
+            {
+                ui.label(trf("{} (predeterminado)", &[&path.display()]));
+            }
+        });
+
+        self.ui_destino(ui);
+        self.ui_vigilancia(ui);
+
+        if let Some(trabajo) = &self.trabajo {
+            trabajo.ui(ui);
+        } else if ui
+            .add_enabled(!self.cola.is_empty(), egui::Button::new(tr("Procesar PDF")))
+            .clicked()
+        {
+            self.iniciar(ui.ctx(), self.cola.archivos().to_vec());
+        }
+
+        if let Some(mensaje) = self.resultados.ui(ui) {
+            self.status = mensaje;
+        }
+    }
+
+    fn encolar(&mut self, paths: Vec<PathBuf>) {
+        if let Err(e) = self.cola.agregar(paths) {
+            self.status = format!("Error: {e}");
+        }
+    }
+
+    /// Queues whatever was dropped on the window.
+    fn recibir_soltados(&mut self, ctx: &egui::Context) {
+        let paths: Vec<PathBuf> = ctx.input(|i| {
+            i.raw
+                .dropped_files
+                .iter()
+                .filter_map(|archivo| archivo.path.clone())
+                .collect()
+        });
+        if !paths.is_empty() {
+            self.vista = Vista::Procesar;
+            self.encolar(paths);
+        }
+    }
+
+    /// Menu to queue again one of the reports processed lately.
+    fn ui_recientes(&mut self, ui: &mut egui::Ui) {
+        let recientes: Vec<PathBuf> = self
+            .recientes
+            .iter()
+            .filter(|path| path.exists())
+            .cloned()
+            .collect();
+        if recientes.is_empty() {
+            return;
+        }
+        let mut elegido = None;
+        egui::ComboBox::from_id_salt("recientes")
+            .selected_text(tr("Recientes"))
+            .show_ui(ui, |ui| {
+                for path in recientes {
+                    let nombre = path.file_name().unwrap_or_default().to_string_lossy();
+                    if ui
+                        .selectable_label(false, nombre)
+                        .on_hover_text(path.display().to_string())
+                        .clicked()
+                    {
+                        elegido = Some(path.clone());
+                    }
+                }
+            });
+        if let Some(path) = elegido {
+            self.encolar(vec![path]);
+        }
+    }
+
+    /// Output folder and file name shared by every xlsx of the run.
+    fn ui_destino(&mut self, ui: &mut egui::Ui) {
+        ui.horizontal(|ui| {
+            if ui.button(tr("Carpeta de salida")).clicked()
+                && let Some(path) = dialogo_en(self.destino.carpeta.as_ref()).pick_folder()
+            {
+                self.destino.carpeta = Some(path);
+            }
+            match &self.destino.carpeta {
+                Some(path) => {
+                    ui.label(path.display().to_string());
+                    if ui.button(tr("Quitar")).clicked() {
+                        self.destino.carpeta = None;
+                    }
+                }
+                None => {
+                    ui.label(tr("Junto a cada reporte"));
                 }
             }
+        });
 
-            if let Some(paths) = &self.pdf_path {
-                ui.label(format!("PDF Seleccionado: {}", paths
+        ui.horizontal(|ui| {
+            ui.label(tr("Nombre del excel:"));
+            let campos = CAMPOS_PLANTILLA
                 .iter()
-                .map(|path| path.display().to_string())
+                .map(|(campo, descripcion)| format!("{campo}: {descripcion}"))
                 .collect::<Vec<String>>()
-                .join("\n")));
+                .join("\n");
+            ui.text_edit_singleline(&mut self.destino.plantilla)
+                .on_hover_text(campos);
+            ui.label(tr("Si ya existe:"));
+            egui::ComboBox::from_id_salt("colision")
+                .selected_text(match self.destino.colision {
+                    Colision::Sufijo => tr("Agregar un número"),
+                    Colision::Sobrescribir => tr("Sobrescribir"),
+                })
+                .show_ui(ui, |ui| {
+                    ui.selectable_value(
+                        &mut self.destino.colision,
+                        Colision::Sufijo,
+                        tr("Agregar un número"),
+                    );
+                    ui.selectable_value(
+                        &mut self.destino.colision,
+                        Colision::Sobrescribir,
+                        tr("Sobrescribir"),
+                    );
+                });
+        });
+        ui.horizontal(|ui| {
+            ui.label(tr("Al terminar:"));
+            egui::ComboBox::from_id_salt("al_terminar")
+                .selected_text(self.al_terminar.nombre())
+                .show_ui(ui, |ui| {
+                    for al_terminar in AlTerminar::TODOS {
+                        ui.selectable_value(
+                            &mut self.al_terminar,
+                            al_terminar,
+                            al_terminar.nombre(),
+                        );
+                    }
+                });
+        });
+    }
+
+    /// Toggle to process on their own the PDFs that land in a folder, with
+    /// the options and output folder above.
+    fn ui_vigilancia(&mut self, ui: &mut egui::Ui) {
+        ui.horizontal(|ui| {
+            let mut vigilar = self.vigilante.is_some();
+            let puede = self.carpeta_vigilada.is_some() || vigilar;
+            if ui
+                .add_enabled(
+                    puede,
+                    egui::Checkbox::new(&mut vigilar, tr("Vigilar carpeta")),
+                )
+                .changed()
+            {
+                if vigilar {
+                    self.vigilar(ui.ctx());
+                } else if let Some(vigilante) = self.vigilante.take() {
+                    vigilante.detener();
+                }
+            }
+            if ui
+                .add_enabled(
+                    self.vigilante.is_none(),
+                    egui::Button::new(tr("Elegir carpeta")),
+                )
+                .clicked()
+                && let Some(path) = dialogo_en(self.carpeta_vigilada.as_ref()).pick_folder()
+            {
+                self.carpeta_vigilada = Some(path);
             }
+            if let Some(carpeta) = &self.carpeta_vigilada {
+                ui.label(carpeta.display().to_string());
+            }
+        });
+        if let Some(vigilante) = &self.vigilante {
+            ui.label(trf(
+                "Vigilando '{}', cada PDF nuevo se procesa solo",
+                &[&vigilante.entrada().display()],
+            ));
+        }
+    }
+
+    fn vigilar(&mut self, ctx: &egui::Context) {
+        let Some(carpeta) = self.carpeta_vigilada.clone() else {
+            return;
+        };
+        if !self.confirmar_sobrescribir() {
+            return;
+        }
+        // A reviewed book belongs to one report, not to every PDF that shows up.
+        let opciones = Opciones {
+            libro_revisado: None,
+            ..self.opciones.clone()
+        };
+        match Vigilancia::nueva(carpeta, self.destino.clone(), opciones) {
+            Ok(vigilancia) => {
+                let ctx = ctx.clone();
+                self.resultados.limpiar();
+                self.vigilante = Some(Vigilante::iniciar(
+                    vigilancia,
+                    Duration::from_secs(vigilancia::INTERVALO_PREDETERMINADO),
+                    move || ctx.request_repaint(),
+                ));
+            }
+            Err(e) => self.status = format!("Error: {e}"),
+        }
+    }
 
-            if ui.button("Procesar PDF").clicked() {
-                if let Some(paths) = &self.pdf_path {
-                    for pdf_path in paths{
-                        match pdf_handling::process_pdf(pdf_path.clone()) {
-                            Ok(_) => self.status = format!("{} procesado\n\n Por favor valida que el excel no contenga errores, este software aún es experimental.\n\nEn caso de haber solicitudes sin manejar, se intentan escribir en un archivo txt\n\t-JAVM", pdf_path.clone().file_name().expect("pdf path").to_string_lossy()),
-                            Err(e) => self.status = format!("Error: {e}"),
+    fn recibir_vigilancia(&mut self) {
+        let Some(vigilante) = &self.vigilante else {
+            return;
+        };
+        for resultado in vigilante.recibir() {
+            match resultado {
+                Ok(procesado) => match procesado.resultado {
+                    Ok((reporte, salida)) => {
+                        self.resultados.agregar(&reporte, &salida);
+                        let nota = self.registrar(&reporte);
+                        self.status = trf(
+                            "Procesado {} de la carpeta vigilada",
+                            &[&procesado.entrada.display()],
+                        ) + &nota;
+                        self.busqueda.cargar(reporte);
+                    }
+                    Err(e) => self.resultados.agregar_error(&[procesado.entrada], e),
+                },
+                Err(e) => self.status = format!("Error: {e}"),
+            }
+        }
+    }
+
+    /// Asks before replacing existing excel, when the destination overwrites them.
+    fn confirmar_sobrescribir(&self) -> bool {
+        self.destino.colision != Colision::Sobrescribir
+            || MessageDialog::new()
+                .set_title(tr("Sobrescribir"))
+                .set_description(tr(
+                    "Los excel que ya existan con el mismo nombre se reemplazarán. ¿Continuar?",
+                ))
+                .set_buttons(MessageButtons::YesNo)
+                .show()
+                == MessageDialogResult::Yes
+    }
+
+    /// Starts processing in the background, every xlsx going to `self.destino`.
+    fn iniciar(&mut self, ctx: &egui::Context, paths: Vec<PathBuf>) {
+        self.status.clear();
+        self.resultados.limpiar();
+        if let Err(e) = self.destino.validar() {
+            self.status = format!("Error: {e}");
+            return;
+        }
+        if !self.confirmar_sobrescribir() {
+            return;
+        }
+        ajustes::agregar_recientes(&mut self.recientes, &paths);
+        let tareas = if self.consolidar {
+            vec![Tarea::Consolidado(paths)]
+        } else {
+            paths.into_iter().map(Tarea::Uno).collect()
+        };
+        self.trabajo = Some(Trabajo::iniciar(
+            tareas,
+            self.opciones.clone(),
+            self.destino.clone(),
+            self.revisar,
+            ctx.clone(),
+        ));
+    }
+
+    /// Handles what the background processing sent since the last frame.
+    fn recibir_eventos(&mut self) {
+        let Some(trabajo) = &mut self.trabajo else {
+            return;
+        };
+        let eventos = trabajo.recibir();
+        let terminado = trabajo.terminado;
+        let revisar = trabajo.revisar;
+        let comparar = trabajo.comparar;
+        for evento in eventos {
+            match evento {
+                Evento::Resultado {
+                    entradas,
+                    resultado,
+                } => match resultado {
+                    Ok((reporte, salida)) if revisar => self.vista_previa.agregar(Pendiente {
+                        entradas,
+                        salida,
+                        reporte,
+                    }),
+                    Ok((reporte, salida)) => {
+                        self.resultados.agregar(&reporte, &salida);
+                        let nota = self.registrar(&reporte);
+                        self.status += &nota;
+                        self.busqueda.cargar(reporte);
+                    }
+                    Err(e) => self.resultados.agregar_error(&entradas, e),
+                },
+                Evento::Comparado(resultado) => {
+                    self.status = match resultado {
+                        Ok(conteo) => trf(
+                            "Comparación lista: {} solicitudes nuevas, {} resueltas, {} modificadas, {} sin procesar en el reporte nuevo y {} con número repetido o vacío",
+                            &[
+                                &conteo.nuevas,
+                                &conteo.resueltas,
+                                &conteo.modificadas,
+                                &conteo.sin_procesar,
+                                &conteo.repetidas,
+                            ],
+                        ),
+                        Err(e) => format!("Error: {e}"),
+                    }
+                }
+                Evento::Fin { cancelado: true } if comparar => {
+                    self.status = tr("Comparación cancelada").to_string();
+                }
+                Evento::Fin { .. } if comparar => {}
+                Evento::Fin { cancelado } if revisar => {
+                    let mut resumen = trf(
+                        "{} reportes listos para revisar, el excel se escribe al exportar",
+                        &[&self.vista_previa.pendientes.len()],
+                    );
+                    if cancelado {
+                        resumen += tr("\n\nProcesamiento cancelado");
+                    }
+                    self.status = resumen + &self.status;
+                    if !self.vista_previa.is_empty() {
+                        self.vista = Vista::Revisar;
+                    }
+                }
+                Evento::Fin { cancelado } => {
+                    let mut resumen = trf(
+                        "{} de {} procesados\n\n Por favor valida que el excel no contenga errores, este software aún es experimental.\n\nEn caso de haber solicitudes sin manejar, quedan en la hoja \"Sin procesar\" del excel\n\t-JAVM",
+                        &[&self.resultados.exitosos(), &self.resultados.len()],
+                    );
+                    if cancelado {
+                        resumen += tr("\n\nProcesamiento cancelado");
+                    }
+                    self.status = resumen + &self.status;
+                    if let Some(e) = self.resultados.abrir(self.al_terminar) {
+                        self.status += &format!("\n\n{e}");
+                    }
+                }
+                _ => {}
+            }
+        }
+        if terminado {
+            self.trabajo = None;
+        }
+    }
+
+    fn vista_revisar(&mut self, ui: &mut egui::Ui) {
+        match self.vista_previa.ui(ui) {
+            Some(Accion::Exportar) => {
+                self.resultados.limpiar();
+                let mut notas = String::new();
+                for pendiente in self.vista_previa.tomar() {
+                    match pdf_handling::escribir(&pendiente.reporte, &pendiente.salida) {
+                        Ok(()) => {
+                            self.resultados
+                                .agregar(&pendiente.reporte, &pendiente.salida);
+                            notas += &self.registrar(&pendiente.reporte);
+                            self.busqueda.cargar(pendiente.reporte);
                         }
+                        Err(e) => self.resultados.agregar_error(&pendiente.entradas, e),
+                    }
+                }
+                self.status = trf(
+                    "{} de {} exportados",
+                    &[&self.resultados.exitosos(), &self.resultados.len()],
+                ) + &notas;
+                if let Some(e) = self.resultados.abrir(self.al_terminar) {
+                    self.status += &format!("\n\n{e}");
+                }
+                self.vista = Vista::Procesar;
+            }
+            Some(Accion::Descartar) => {
+                self.vista_previa.tomar();
+                self.status = tr("Revisión descartada, no se escribió ningún excel").to_string();
+                self.vista = Vista::Procesar;
+            }
+            Some(Accion::Mensaje(mensaje)) => self.status = mensaje,
+            None => {}
+        }
+    }
+
+    fn vista_comparar(&mut self, ui: &mut egui::Ui) {
+        ui.horizontal(|ui| {
+            if ui.button(tr("Reporte anterior")).clicked()
+                && let Some(path) = seleccionar_pdf()
+            {
+                self.reporte_anterior = Some(path);
+            }
+            if let Some(path) = &self.reporte_anterior {
+                ui.label(path.display().to_string());
+            }
+        });
+        ui.horizontal(|ui| {
+            if ui.button(tr("Reporte nuevo")).clicked()
+                && let Some(path) = seleccionar_pdf()
+            {
+                self.reporte_nuevo = Some(path);
+            }
+            if let Some(path) = &self.reporte_nuevo {
+                ui.label(path.display().to_string());
+            }
+        });
+
+        if let Some(trabajo) = &self.trabajo {
+            if trabajo.comparar {
+                trabajo.ui(ui);
+            } else {
+                ui.label(tr("Espere a que termine el procesamiento para comparar"));
+            }
+        } else if ui.button(tr("Comparar reportes")).clicked()
+            && let (Some(anterior), Some(nuevo)) = (&self.reporte_anterior, &self.reporte_nuevo)
+        {
+            let file_stem = nuevo.file_stem().unwrap_or_default().to_string_lossy();
+            if let Some(salida) = FileDialog::new()
+                .add_filter("Excel", &["xlsx"])
+                .set_file_name(format!("{file_stem}_cambios.xlsx"))
+                .save_file()
+            {
+                self.status.clear();
+                self.trabajo = Some(Trabajo::aparte(
+                    Tarea::Comparar {
+                        anterior: anterior.clone(),
+                        nuevo: nuevo.clone(),
+                        salida,
+                    },
+                    ui.ctx().clone(),
+                ));
+            }
+        }
+    }
+
+    fn vista_historial(&mut self, ui: &mut egui::Ui) {
+        ui.horizontal(|ui| {
+            ui.label(tr("Identificación, nombre o número de solicitud:"));
+            let respuesta = ui.text_edit_singleline(&mut self.consulta_historial);
+            let enter = respuesta.lost_focus() && ui.input(|i| i.key_pressed(egui::Key::Enter));
+            if (ui.button(tr("Buscar")).clicked() || enter)
+                && !self.consulta_historial.trim().is_empty()
+            {
+                let consulta = self.consulta_historial.clone();
+                match self.historial().and_then(|h| h.buscar(&consulta)) {
+                    Ok(registros) => {
+                        self.status = trf("{} solicitudes encontradas", &[&registros.len()]);
+                        self.resultados_historial = registros;
+                    }
+                    Err(e) => self.status = format!("Error: {e}"),
+                }
+            }
+        });
+
+        egui::ScrollArea::both().max_height(400.0).show(ui, |ui| {
+            egui::Grid::new("historial")
+                .striped(true)
+                .num_columns(6)
+                .show(ui, |ui| {
+                    for header in [
+                        tr("Número de solicitud"),
+                        tr("Fecha de solicitud"),
+                        tr("Tipo"),
+                        tr("Identificación"),
+                        tr("Nombre del estudiante"),
+                        tr("Reportes"),
+                    ] {
+                        ui.strong(header);
+                    }
+                    ui.end_row();
+                    for registro in &self.resultados_historial {
+                        ui.label(&registro.numero_solicitud)
+                            .on_hover_text(registro.motivos.as_deref().unwrap_or_default());
+                        ui.label(&registro.fecha_de_solicitud);
+                        ui.label(tr(&registro.tipo));
+                        ui.label(&registro.identificacion);
+                        ui.label(&registro.nombre_del_estudiante)
+                            .on_hover_text(&registro.plan_de_estudios);
+                        ui.label(&registro.reportes);
+                        ui.end_row();
+                    }
+                });
+        });
+    }
+}
+
+impl eframe::App for PdfProcessorApp {
+    fn save(&mut self, storage: &mut dyn eframe::Storage) {
+        eframe::set_value(storage, eframe::APP_KEY, &self.ajustes());
+    }
+
+    fn update(&mut self, ctx: &egui::Context, _frame: &mut eframe::Frame) {
+        self.recibir_eventos();
+        self.recibir_vigilancia();
+        self.recibir_soltados(ctx);
+
+        if ctx.input(|i| !i.raw.hovered_files.is_empty()) {
+            egui::TopBottomPanel::bottom("soltar").show(ctx, |ui| {
+                ui.strong(tr("Suelte los PDFs o carpetas para agregarlos a la cola"));
+            });
+        }
+
+        egui::TopBottomPanel::bottom("bitacora").show(ctx, bitacora::ui);
+
+        egui::CentralPanel::default().show(ctx, |ui| {
+            ui.heading(tr("Procesador de Reportes de Agenda del SIA"));
+
+            ui.horizontal(|ui| {
+                ui.selectable_value(&mut self.vista, Vista::Procesar, tr("Procesar"));
+                ui.selectable_value(&mut self.vista, Vista::Comparar, tr("Comparar"));
+                ui.selectable_value(&mut self.vista, Vista::Historial, tr("Historial"));
+                ui.selectable_value(&mut self.vista, Vista::Buscar, tr("Buscar"));
+                ui.selectable_value(&mut self.vista, Vista::Reglas, tr("Reglas"));
+                if !self.vista_previa.is_empty() {
+                    let titulo = trf("Revisar ({})", &[&self.vista_previa.pendientes.len()]);
+                    ui.selectable_value(&mut self.vista, Vista::Revisar, titulo);
+                }
+                ui.with_layout(egui::Layout::right_to_left(egui::Align::Center), |ui| {
+                    let anterior = self.tema;
+                    egui::ComboBox::from_id_salt("tema")
+                        .selected_text(self.tema.nombre())
+                        .show_ui(ui, |ui| {
+                            for tema in Tema::TODOS {
+                                ui.selectable_value(&mut self.tema, tema, tema.nombre());
+                            }
+                        });
+                    ui.label(tr("Tema:"));
+                    if self.tema != anterior {
+                        self.tema.aplicar(ui.ctx());
+                    }
+
+                    let anterior = self.idioma;
+                    egui::ComboBox::from_id_salt("idioma")
+                        .selected_text(self.idioma.nombre())
+                        .show_ui(ui, |ui| {
+                            for idioma in Idioma::TODOS {
+                                ui.selectable_value(&mut self.idioma, idioma, idioma.nombre());
+                            }
+                        });
+                    ui.label(tr("Idioma:"));
+                    if self.idioma != anterior {
+                        idioma::usar(self.idioma);
+                    }
+                });
+            });
+            ui.separator();
+
+            match self.vista {
+                Vista::Procesar => self.vista_procesar(ui),
+                Vista::Comparar => self.vista_comparar(ui),
+                Vista::Historial => self.vista_historial(ui),
+                Vista::Revisar => self.vista_revisar(ui),
+                Vista::Buscar => self.busqueda.ui(
+                    ui,
+                    self.vista_previa
+                        .pendientes
+                        .iter()
+                        .map(|pendiente| &pendiente.reporte),
+                ),
+                Vista::Reglas => {
+                    if let Some(mensaje) = self.probador.ui(ui) {
+                        self.status = mensaje;
                     }
                 }
             }
@@ -85,16 +820,35 @@ impl eframe::App for PdfProcessorApp {
 }
 
 fn main() -> Result<(), eframe::Error> {
+    let cli = cli::Cli::parse();
+    bitacora::iniciar(cli.comando.is_some());
+    if let Some(comando) = cli.comando {
+        if let Err(e) = cli::ejecutar(comando) {
+            log::error!("{e}");
+            std::process::exit(1);
+        }
+        return Ok(());
+    }
+
     let icon = load_icon().unwrap();
     let options = eframe::NativeOptions {
         viewport: egui::ViewportBuilder::default().with_icon(icon),
+        persistence_path: ajustes::ruta_predeterminada(),
         ..Default::default()
     }; // Start app with icon
 
     eframe::run_native(
         "Procesador de PDF de Reporte de Agenda a Excel",
         options,
-        Box::new(|_cc| Ok(Box::new(PdfProcessorApp::default()))),
+        Box::new(|cc| {
+            let ajustes: Ajustes = cc
+                .storage
+                .and_then(|storage| eframe::get_value(storage, eframe::APP_KEY))
+                .unwrap_or_default();
+            ajustes.tema.aplicar(&cc.egui_ctx);
+            idioma::usar(ajustes.idioma);
+            Ok(Box::new(PdfProcessorApp::new(ajustes)))
+        }),
     )?;
     Ok(())
 }
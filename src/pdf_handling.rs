@@ -1,25 +1,147 @@
-use std::io::Write;
-use chrono::{NaiveDate, ParseError};
-use std::sync::LazyLock;
-use regex::Regex;
-use rust_xlsxwriter::{Format, Workbook, XlsxError};
+use crate::bitacora;
+use crate::correcciones::{self, Correcciones};
+use crate::idioma::{self, tr, trf};
+use crate::reglas::{self, Regla, Reglas};
+use crate::revisiones::{self, Conflicto, LibroRevisado};
+use crate::traza::{Traza, ruta_traza};
+use chrono::{DateTime, Local, NaiveDate, ParseError};
+use regex::{Captures, Regex};
+use rust_xlsxwriter::{Color, DocProperties, Format, Note, Url, Workbook, XlsxError};
+use sha2::{Digest, Sha256};
 use std::collections::{HashMap, VecDeque};
 use std::fs::File;
-use std::path::PathBuf;
-use rfd::FileDialog;
-
+use std::io::Write;
+use std::panic::AssertUnwindSafe;
+use std::path::{Path, PathBuf};
+use std::sync::LazyLock;
 
 #[derive(Debug, Default)]
 pub struct Solicitud {
-    nombre_del_estudiante: String,
-    plan_de_estudios: String,
-    numero_solicitud: String,
-    fecha_de_solicitud: NaiveDate,
-    identificacion: usize,
-    motivos: Option<String>,
-    adjuntos: Option<usize>,
-    materias: Option<String>,
-    periodo: Option<String>,
+    pub nombre_del_estudiante: String,
+    pub plan_de_estudios: String,
+    pub numero_solicitud: String,
+    pub fecha_de_solicitud: NaiveDate,
+    pub identificacion: usize,
+    pub motivos: Option<String>,
+    pub adjuntos: Option<usize>,
+    pub materias: Option<String>,
+    pub periodo: Option<String>,
+    /// Report text the request was parsed from.
+    pub texto_fuente: String,
+    /// First and last PDF pages the request spans.
+    pub paginas: Option<(usize, usize)>,
+    /// Indices in `Reporte::procedencias` of its reports, the first one giving the data.
+    pub origenes: Vec<usize>,
+    /// Reviewer decision from an earlier workbook.
+    pub decision: String,
+    /// Reviewer notes from an earlier workbook.
+    pub notas: String,
+    /// PDF value of the fields corrected by hand.
+    pub originales: HashMap<Campo, String>,
+}
+
+/// Columns a request row can have in the Excel.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum Campo {
+    NombreDelEstudiante,
+    PlanDeEstudios,
+    NumeroSolicitud,
+    FechaDeSolicitud,
+    Identificacion,
+    Adjuntos,
+    Materias,
+    Periodo,
+    Motivos,
+    Paginas,
+}
+
+/// Problems highlighted in the Excel for the reviewer.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Revision {
+    /// A required field is empty.
+    Falta,
+    /// Too long or containing other labels, likely an overreaching regex.
+    Sospechoso,
+}
+
+// Report labels that should never show up inside an extracted value.
+const ETIQUETAS_DE_SECCION: [&str; 6] = [
+    "nombre del estudiante",
+    "número y fecha de la solicitud",
+    "anexar otros documentos físicos",
+    "materias relacionadas a la solicitud",
+    "periodo para el que solicita",
+    "solicitud estudiante",
+];
+
+impl Campo {
+    pub const TODOS: [Campo; 10] = [
+        Campo::NombreDelEstudiante,
+        Campo::PlanDeEstudios,
+        Campo::NumeroSolicitud,
+        Campo::FechaDeSolicitud,
+        Campo::Identificacion,
+        Campo::Adjuntos,
+        Campo::Materias,
+        Campo::Periodo,
+        Campo::Motivos,
+        Campo::Paginas,
+    ];
+
+    /// Accepts the header in any language, so older or foreign workbooks
+    /// and corrections files still work.
+    pub fn desde_encabezado(encabezado: &str) -> Option<Campo> {
+        Campo::TODOS
+            .into_iter()
+            .find(|campo| idioma::todas(campo.encabezado_es()).contains(&encabezado.trim()))
+    }
+
+    /// Header in the current language.
+    pub fn encabezado(&self) -> &'static str {
+        tr(self.encabezado_es())
+    }
+
+    fn encabezado_es(&self) -> &'static str {
+        match self {
+            Campo::NombreDelEstudiante => "Nombre del estudiante",
+            Campo::PlanDeEstudios => "Plan de estudios",
+            Campo::NumeroSolicitud => "Número de solicitud",
+            Campo::FechaDeSolicitud => "Fecha de solicitud",
+            Campo::Identificacion => "Identificación",
+            Campo::Adjuntos => "Adjuntos",
+            Campo::Materias => "Materias",
+            Campo::Periodo => "Periodo",
+            Campo::Motivos => "Motivos",
+            Campo::Paginas => "Página(s)",
+        }
+    }
+
+    fn largo_maximo(&self) -> usize {
+        match self {
+            Campo::Motivos => 2000,
+            Campo::Materias => 600,
+            _ => 200,
+        }
+    }
+
+    pub fn revisar(&self, valor: &str) -> Option<Revision> {
+        let valor = valor.trim();
+        if matches!(self, Campo::Adjuntos | Campo::Paginas) {
+            return None;
+        }
+        if valor.is_empty() {
+            return Some(Revision::Falta);
+        }
+        let minusculas = valor.to_lowercase();
+        if valor.chars().count() > self.largo_maximo()
+            || ETIQUETAS_DE_SECCION
+                .iter()
+                .any(|etiqueta| minusculas.contains(etiqueta))
+        {
+            return Some(Revision::Sospechoso);
+        }
+        None
+    }
 }
 
 pub struct SolicitudIterator<'a> {
@@ -28,35 +150,55 @@ pub struct SolicitudIterator<'a> {
 }
 
 impl Iterator for SolicitudIterator<'_> {
-    type Item = String;
+    type Item = (Campo, String);
 
     fn next(&mut self) -> Option<Self::Item> {
         self.index += 1;
         match self.index {
-            1 => Some(self.solicitud.nombre_del_estudiante.clone()),
-            2 => Some(self.solicitud.plan_de_estudios.clone()),
-            3 => Some(self.solicitud.numero_solicitud.clone()),
-            4 => Some(
+            1 => Some((
+                Campo::NombreDelEstudiante,
+                self.solicitud.nombre_del_estudiante.clone(),
+            )),
+            2 => Some((
+                Campo::PlanDeEstudios,
+                self.solicitud.plan_de_estudios.clone(),
+            )),
+            3 => Some((
+                Campo::NumeroSolicitud,
+                self.solicitud.numero_solicitud.clone(),
+            )),
+            4 => Some((
+                Campo::FechaDeSolicitud,
                 self.solicitud
                     .fecha_de_solicitud
                     .format("%d/%m/%Y")
                     .to_string(),
-            ),
-            5 => Some(self.solicitud.identificacion.to_string()),
+            )),
+            5 => Some((
+                Campo::Identificacion,
+                self.solicitud.identificacion.to_string(),
+            )),
             6 => match &self.solicitud.adjuntos {
-                Some(anexos) => Some(anexos.to_string()),
+                Some(anexos) => Some((Campo::Adjuntos, anexos.to_string())),
                 None => self.next(),
             },
             7 => match &self.solicitud.materias {
-                Some(materias) => Some(materias.to_string()),
+                Some(materias) => Some((Campo::Materias, materias.to_string())),
                 None => self.next(),
             },
             8 => match &self.solicitud.periodo {
-                Some(periodo) => Some(periodo.to_string()),
+                Some(periodo) => Some((Campo::Periodo, periodo.to_string())),
                 None => self.next(),
             },
             9 => match &self.solicitud.motivos {
-                Some(motivos) => Some(motivos.to_string()),
+                Some(motivos) => Some((Campo::Motivos, motivos.to_string())),
+                None => self.next(),
+            },
+            10 => match self.solicitud.paginas {
+                Some((primera, ultima)) if primera == ultima => {
+                    Some((Campo::Paginas, primera.to_string()))
+                }
+                Some((primera, ultima)) => Some((Campo::Paginas, format!("{primera}-{ultima}"))),
                 None => self.next(),
             },
             _ => None,
@@ -65,12 +207,114 @@ impl Iterator for SolicitudIterator<'_> {
 }
 
 impl Solicitud {
-    fn iter(&self) -> SolicitudIterator<'_> {
+    pub fn iter(&self) -> SolicitudIterator<'_> {
         SolicitudIterator {
             solicitud: self,
             index: 0,
         }
     }
+
+    /// Value of `campo` as written in the Excel, `None` if this request doesn't have it.
+    pub fn valor(&self, campo: Campo) -> Option<String> {
+        self.iter()
+            .find(|(c, _)| *c == campo)
+            .map(|(_, valor)| valor)
+    }
+
+    /// Sets `campo` from its Excel representation.
+    pub fn asignar(&mut self, campo: Campo, valor: &str) -> Result<(), String> {
+        let valor = valor.trim();
+        match campo {
+            Campo::NombreDelEstudiante => self.nombre_del_estudiante = valor.to_string(),
+            Campo::PlanDeEstudios => self.plan_de_estudios = valor.to_string(),
+            Campo::NumeroSolicitud => self.numero_solicitud = valor.to_string(),
+            Campo::FechaDeSolicitud => {
+                self.fecha_de_solicitud =
+                    parse_date(valor).map_err(|e| format!("Error parsing date '{valor}': {e}"))?
+            }
+            Campo::Identificacion => {
+                self.identificacion = sin_espacios(valor)
+                    .parse()
+                    .map_err(|e| format!("Error parsing identificacion '{valor}': {e}"))?
+            }
+            Campo::Adjuntos => {
+                self.adjuntos = Some(
+                    valor
+                        .parse()
+                        .map_err(|e| format!("Error parsing adjuntos '{valor}': {e}"))?,
+                )
+            }
+            Campo::Materias => self.materias = Some(valor.to_string()),
+            Campo::Periodo => self.periodo = Some(valor.to_string()),
+            Campo::Motivos => self.motivos = Some(valor.to_string()),
+            Campo::Paginas => return Err(tr("Las páginas no se pueden corregir").to_string()),
+        }
+        Ok(())
+    }
+
+    /// Sets `campo` by hand, remembering what the PDF said so the cell can be
+    /// marked as corrected.
+    pub fn corregir(&mut self, campo: Campo, valor: &str) -> Result<(), String> {
+        let original = self.valor(campo).unwrap_or_default();
+        self.asignar(campo, valor)?;
+        self.originales.entry(campo).or_insert(original);
+        Ok(())
+    }
+
+    /// Keeps exactly the optional fields of `tipo`, so a request moved to
+    /// another type lines up with the columns of its new sheet.
+    pub fn ajustar_a_tipo(&mut self, tipo: &str) {
+        let campos = campos_del_tipo(tipo);
+        if campos.contains(&Campo::Adjuntos) {
+            self.adjuntos.get_or_insert(0);
+        } else {
+            self.adjuntos = None;
+        }
+        for (campo, valor) in [
+            (Campo::Materias, &mut self.materias),
+            (Campo::Periodo, &mut self.periodo),
+            (Campo::Motivos, &mut self.motivos),
+        ] {
+            if campos.contains(&campo) {
+                valor.get_or_insert_default();
+            } else {
+                *valor = None;
+            }
+        }
+    }
+
+    /// Value of `campo` as extracted from the PDF, before any correction.
+    pub fn valor_original(&self, campo: Campo) -> Option<String> {
+        match self.originales.get(&campo) {
+            Some(original) => Some(original.clone()),
+            None => self.valor(campo),
+        }
+    }
+}
+
+/// Columns of the sheet for requests of `tipo`, in order.
+pub fn campos_del_tipo(tipo: &str) -> Vec<Campo> {
+    let mut campos = vec![
+        Campo::NombreDelEstudiante,
+        Campo::PlanDeEstudios,
+        Campo::NumeroSolicitud,
+        Campo::FechaDeSolicitud,
+        Campo::Identificacion,
+    ];
+    if tipo.starts_with(CEA) {
+        campos.extend([Campo::Adjuntos, Campo::Materias]);
+    } else if tipo.starts_with(CS) || tipo.starts_with(ACM) {
+        campos.extend([Campo::Adjuntos, Campo::Periodo]);
+    } else if tipo.starts_with(RTG) {
+        campos.push(Campo::Adjuntos);
+    }
+    if !tipo.starts_with(RTG) {
+        campos.push(Campo::Motivos);
+    }
+    if tipo != ANOTACIONES {
+        campos.push(Campo::Paginas);
+    }
+    campos
 }
 
 pub fn parse_date(date_str: &str) -> Result<NaiveDate, ParseError> {
@@ -89,51 +333,15 @@ static SOLICITUD_SECCION_RE: LazyLock<Regex> =
     LazyLock::new(|| Regex::new(SOLICITUD_ESTUDIANTE_SEP).unwrap());
 
 const DOC_ANEX_DOC: &str = r"(\s*documento\s+anexo\s+Documento\s*)";
-const ANOTACIONES: &str = "ANOTACIONES";
-
-const CEA: &str = "CANCELACIÓN EXTEMP. ASIGNATURAS";
-const RS_RE_CEA: &str = concat!(
-    r"(?s)nombre del estudiante\s*(.+)\s*identificación\s*(\d+\s*\d*)\s*plan de estudios\s*(.+?)\s*número y fecha de la solicitud\s*([A-Z\d\s-]+)\s+(\d\s*\d/\d{2}/\d{4}|\d{2}/\d{2}/\d{2})\s*",
-    r"(?:motivos\s*(.*))(?:anexar otros documentos físicos\s*(.*))",
-    r"(?:materias relacionadas a la solicitud\s*(?:asignatura grp nombre)?(.*))",
-);
-static SOLICITUD_CEA: LazyLock<Regex> = LazyLock::new(|| {
-    Regex::new(RS_RE_CEA)
-        .map_err(|e| format!("Error compiling regex: {e}"))
-        .unwrap()
-});
+pub const ANOTACIONES: &str = "ANOTACIONES";
 
-const CS: &str = "CANCELACIÓN SEMESTRE";
-const RS_RE_CS: &str = concat!(
-    r"(?s)nombre del estudiante\s*(.+?)\s*identificación\s*(\d+\s*\d*)\s*plan de estudios\s*(.+?)\s*número y fecha de la solicitud\s*([A-Z\d\s-]+)\s+(\d\s*\d/\d{2}/\d{4}|\d{2}/\d{2}/\d{2})\s*",
-    r"(?:motivos\s*(.*))(?:anexar otros documentos físicos\s*(.*))",
-    r"(?:periodo para el que solicita cancelación de semestre\s*(.*))",
-);
-static SOLICITUD_CS: LazyLock<Regex> = LazyLock::new(|| {
-    Regex::new(RS_RE_CS)
-        .map_err(|e| format!("Error compiling regex: {e}"))
-        .unwrap()
-});
+pub const CEA: &str = "CANCELACIÓN EXTEMP. ASIGNATURAS";
 
-const ACM: &str = "AUTORIZACIÓN CARGA MÍNIMA";
-const RS_RE_ACM: &str = concat!(
-    r"(?s)nombre del estudiante\s*(.+?)\s*identificación\s*(\d+\s*\d*)\s*plan de estudios\s*(.+?)\s*número y fecha de la solicitud\s*([A-Z\d\s-]+)\s+(\d\s*\d/\d{2}/\d{4}|\d{2}/\d{2}/\d{2})\s*",
-    r"(?:motivos\s*(.*))(?:anexar otros documentos físicos\s*(.*))",
-    r"(?:periodo para el que solicita carga mínima\s*(.*))",
-);
-static SOLICITUD_ACM: LazyLock<Regex> = LazyLock::new(|| {
-    Regex::new(RS_RE_ACM)
-        .map_err(|e| format!("Error compiling regex: {e}"))
-        .unwrap()
-});
+pub const CS: &str = "CANCELACIÓN SEMESTRE";
 
-const RTG: &str = "REGISTRO TRABAJO GRADO";
-const RS_RE_RTG: &str = r"(?s)nombre del estudiante\s*(.+?)\s*identificación\s*(\d+\s*\d*)\s*plan de estudios\s*(.+?)\s*número y fecha de la solicitud\s*([^ ]+)\s+(\d\s*\d/\d{2}/\d{4}|\d{2}/\d{2}/\d{2})\s*(?:anexar otros documentos físicos\s*(.*))";
-static SOLICITUD_RTG: LazyLock<Regex> = LazyLock::new(|| {
-    Regex::new(RS_RE_RTG)
-        .map_err(|e| format!("Error compiling regex: {e}"))
-        .unwrap()
-});
+pub const ACM: &str = "AUTORIZACIÓN CARGA MÍNIMA";
+
+pub const RTG: &str = "REGISTRO TRABAJO GRADO";
 
 static DOC_ANEX_DOC_RE: LazyLock<Regex> = LazyLock::new(|| {
     Regex::new(DOC_ANEX_DOC)
@@ -141,20 +349,371 @@ static DOC_ANEX_DOC_RE: LazyLock<Regex> = LazyLock::new(|| {
         .unwrap()
 });
 
-type SolicitudMap = HashMap<String, Vec<Solicitud>>;
+pub type SolicitudMap = HashMap<String, Vec<Solicitud>>;
 
-pub fn read_and_extract_data(
-    pdf_contents: &str,
-) -> Result<(SolicitudMap, Vec<&str>), String> {
-    // Split anotaciones from the rest of sections
+/// Identifies the set of rules used to parse a report: the first characters of
+/// the SHA-256 of every pattern, so it changes whenever a pattern does.
+pub fn version_reglas() -> String {
+    let reglas = reglas::activas();
+    let mut hasher = Sha256::new();
+    for patron in [ANOTACIONES_SEP, SECTION_STR, SOLICITUD_ESTUDIANTE_SEP]
+        .into_iter()
+        .chain(Regla::TODAS.map(|regla| reglas.patron(regla)))
+        .chain([DOC_ANEX_DOC])
+    {
+        hasher.update(patron.as_bytes());
+    }
+    hex(&hasher.finalize())[..12].to_string()
+}
+
+fn hex(bytes: &[u8]) -> String {
+    bytes.iter().map(|b| format!("{b:02x}")).collect()
+}
+
+/// Hex SHA-256 of a file's contents, what identifies a report across renames.
+pub fn sha256(bytes: &[u8]) -> String {
+    hex(&Sha256::digest(bytes))
+}
+
+/// Where a workbook came from, so any spreadsheet can be traced back to its input.
+#[derive(Debug, Clone)]
+pub struct Procedencia {
+    pub pdf_path: PathBuf,
+    pub sha256: String,
+    pub version: &'static str,
+    pub version_reglas: String,
+    pub procesado: DateTime<Local>,
+}
+
+impl Procedencia {
+    pub fn new(pdf_path: &Path, bytes: &[u8]) -> Self {
+        Self {
+            pdf_path: pdf_path.to_path_buf(),
+            sha256: sha256(bytes),
+            version: env!("CARGO_PKG_VERSION"),
+            version_reglas: version_reglas(),
+            procesado: Local::now(),
+        }
+    }
 
-    let pdf_contents: Vec<&str> = pdf_contents.split(ANOTACIONES_SEP).collect();
-    let mut unhandled: Vec<&str> = Vec::new();
-    let anotaciones = pdf_contents[1];
-    let pdf_contents = pdf_contents[0];
+    pub fn archivo(&self) -> String {
+        self.pdf_path
+            .file_name()
+            .unwrap_or_default()
+            .to_string_lossy()
+            .to_string()
+    }
+
+    fn propiedades(&self) -> Vec<(&'static str, String)> {
+        vec![
+            ("Archivo fuente", self.archivo()),
+            ("SHA-256 del PDF", self.sha256.clone()),
+            ("Versión de reps-sia", self.version.to_string()),
+            ("Versión de reglas", self.version_reglas.clone()),
+            (
+                "Procesado",
+                self.procesado.format("%Y-%m-%d %H:%M:%S %:z").to_string(),
+            ),
+        ]
+    }
+}
+
+/// Everything parsed from one or more reports, ready to be written.
+#[derive(Debug, Default)]
+pub struct Reporte {
+    pub procedencias: Vec<Procedencia>,
+    pub solicitudes: SolicitudMap,
+    pub sin_procesar: Vec<SinProcesar>,
+    /// Reviewer corrections that could not be carried over because the PDF changed.
+    pub conflictos: Vec<Conflicto>,
+}
+
+const REPORTE_DE_ORIGEN: &str = "Reporte de origen";
+const PDF: &str = "PDF";
+
+impl Reporte {
+    /// PDF of the report at `origen`, or of the first one.
+    fn pdf_path(&self, origen: Option<usize>) -> &Path {
+        let origen = origen
+            .unwrap_or(0)
+            .min(self.procedencias.len().saturating_sub(1));
+        self.procedencias[origen].pdf_path.as_path()
+    }
+
+    /// File names of the reports at `origenes`, comma separated.
+    fn nombres(&self, origenes: &[usize]) -> String {
+        origenes
+            .iter()
+            .filter_map(|&i| self.procedencias.get(i))
+            .map(Procedencia::archivo)
+            .collect::<Vec<String>>()
+            .join(", ")
+    }
+
+    /// Merges several reports into one. Requests that appear in more than one
+    /// report keep the data of the first one and remember every report they
+    /// appeared in.
+    pub fn consolidar(reportes: Vec<Reporte>) -> Reporte {
+        let mut consolidado = Reporte {
+            procedencias: Vec::new(),
+            solicitudes: HashMap::new(),
+            sin_procesar: Vec::new(),
+            conflictos: Vec::new(),
+        };
+        // numero_solicitud -> (sheet, row) of the request already merged.
+        let mut vistas: HashMap<String, (String, usize)> = HashMap::new();
+
+        for reporte in reportes {
+            let desplazamiento = consolidado.procedencias.len();
+            consolidado.procedencias.extend(reporte.procedencias);
+
+            let mut ordered_data: Vec<(String, Vec<Solicitud>)> =
+                reporte.solicitudes.into_iter().collect();
+            ordered_data.sort_by(|a, b| a.0.cmp(&b.0));
+            for (tipo, solicitudes) in ordered_data {
+                for mut solicitud in solicitudes {
+                    for origen in solicitud.origenes.iter_mut() {
+                        *origen += desplazamiento;
+                    }
+                    let numero = solicitud.numero_solicitud.clone();
+                    if !numero.is_empty()
+                        && let Some((tipo_visto, fila)) = vistas.get(&numero)
+                    {
+                        let existente = &mut consolidado
+                            .solicitudes
+                            .get_mut(tipo_visto)
+                            .expect("merged sheet exists")[*fila];
+                        existente.origenes.extend(solicitud.origenes);
+                        continue;
+                    }
+                    let hoja = consolidado.solicitudes.entry(tipo.clone()).or_default();
+                    if !numero.is_empty() {
+                        vistas.insert(numero, (tipo.clone(), hoja.len()));
+                    }
+                    hoja.push(solicitud);
+                }
+            }
+
+            consolidado
+                .sin_procesar
+                .extend(reporte.sin_procesar.into_iter().map(|mut chunk| {
+                    chunk.origen += desplazamiento;
+                    chunk
+                }));
+        }
+
+        consolidado
+    }
+}
+
+/// Page numbers (1-based) spanned by `chunk`, a slice of `texto`.
+/// `inicios_de_pagina` holds the byte offset in `texto` where each page starts.
+fn paginas_de(texto: &str, chunk: &str, inicios_de_pagina: &[usize]) -> Option<(usize, usize)> {
+    if inicios_de_pagina.is_empty() {
+        return None;
+    }
+    let inicio = (chunk.as_ptr() as usize).checked_sub(texto.as_ptr() as usize)?;
+    let fin = inicio + chunk.len().saturating_sub(1);
+    let pagina = |offset: usize| inicios_de_pagina.partition_point(|&i| i <= offset).max(1);
+    Some((pagina(inicio), pagina(fin)))
+}
+
+/// Extracts the text of every page and joins it, returning where each page starts.
+///
+/// `pdf_extract::extract_text_from_mem_by_pages` stops quietly at the first
+/// page it can't read, so pages are extracted one by one here and a failing
+/// page fails the whole report, as extracting it in one go used to. Most
+/// broken pages make `pdf_extract` panic rather than fail, so those are
+/// caught too instead of taking the worker thread down.
+pub fn extract_pages(bytes: &[u8]) -> Result<(String, Vec<usize>), String> {
+    let mut doc = pdf_extract::Document::load_mem(bytes)
+        .map_err(|e| format!("Error extracting text from PDF: {e}"))?;
+    if doc.is_encrypted() {
+        doc.decrypt("")
+            .map_err(|e| format!("Error extracting text from PDF: {e}"))?;
+    }
+    let paginas = doc.get_pages();
+    let mut texto = String::new();
+    let mut inicios_de_pagina = Vec::with_capacity(paginas.len());
+    for &numero in paginas.keys() {
+        let mut pagina = String::new();
+        let extraida = std::panic::catch_unwind(AssertUnwindSafe(|| {
+            pdf_extract::output_doc_page(
+                &doc,
+                &mut pdf_extract::PlainTextOutput::new(&mut pagina),
+                numero,
+            )
+        }));
+        match extraida {
+            Ok(Ok(())) => {}
+            Ok(Err(e)) => {
+                return Err(format!(
+                    "Error extracting text from page {numero} of the PDF: {e}"
+                ));
+            }
+            Err(_) => {
+                return Err(format!(
+                    "Error extracting text from page {numero} of the PDF: unsupported content"
+                ));
+            }
+        }
+        inicios_de_pagina.push(texto.len());
+        texto.push_str(&pagina);
+    }
+    Ok((texto, inicios_de_pagina))
+}
+
+fn sin_espacios(texto: &str) -> String {
+    texto.split_whitespace().collect::<Vec<&str>>().join("")
+}
+
+/// Fields shared by every request type, groups 1 to 5 of every request regex.
+/// `numero_sin_espacios` removes the whitespace `pdf_extract` tends to insert in
+/// request numbers; otherwise the number is only trimmed.
+fn solicitud_base(
+    captures: &Captures,
+    chunk: &str,
+    paginas: Option<(usize, usize)>,
+    numero_sin_espacios: bool,
+) -> Result<Solicitud, String> {
+    let grupo = |i: usize| captures.get(i).map_or("", |m| m.as_str());
+
+    let nombre_del_estudiante = grupo(1).trim().to_string();
+    let identificacion_str = sin_espacios(grupo(2));
+    let identificacion = identificacion_str
+        .parse()
+        .map_err(|e| format!("Error parsing identificacion '{identificacion_str}': {e}"))?;
+    let plan_de_estudios = grupo(3).trim().into();
+    let numero_solicitud = if numero_sin_espacios {
+        sin_espacios(grupo(4))
+    } else {
+        grupo(4).trim().to_string()
+    };
+    let fecha_de_solicitud_str = sin_espacios(grupo(5));
+    let fecha_de_solicitud = parse_date(&fecha_de_solicitud_str)
+        .map_err(|e| format!("Error parsing date '{fecha_de_solicitud_str}': {e}"))?;
+
+    Ok(Solicitud {
+        nombre_del_estudiante,
+        plan_de_estudios,
+        numero_solicitud,
+        fecha_de_solicitud,
+        identificacion,
+        texto_fuente: chunk.trim().to_string(),
+        paginas,
+        origenes: vec![0],
+        ..Default::default()
+    })
+}
+
+/// Chunk of the report that could not be turned into a `Solicitud`.
+#[derive(Debug)]
+pub struct SinProcesar {
+    /// Position of the chunk in the report, starting at 1.
+    pub posicion: usize,
+    pub texto: String,
+    pub paginas: Option<(usize, usize)>,
+    /// Why it was not handled.
+    pub motivo: String,
+    /// Index of the report it came from in `Reporte::procedencias`.
+    pub origen: usize,
+}
+
+// Loose patterns used to salvage what we can from chunks no request regex matched.
+static NOMBRE_PARCIAL_RE: LazyLock<Regex> =
+    LazyLock::new(|| Regex::new(r"(?is)nombre del estudiante\s*(.+?)\s*identificación").unwrap());
+static IDENTIFICACION_PARCIAL_RE: LazyLock<Regex> =
+    LazyLock::new(|| Regex::new(r"(?i)identificación\s*(\d+\s*\d*)").unwrap());
+static NUMERO_FECHA_PARCIAL_RE: LazyLock<Regex> = LazyLock::new(|| {
+    Regex::new(
+        r"(?i)número y fecha de la solicitud\s*([A-Z\d\s-]+?)\s+(\d\s*\d/\d{2}/\d{4}|\d{2}/\d{2}/\d{2})",
+    )
+    .unwrap()
+});
+
+/// Fields `SinProcesar::campos_parciales` looks for, in the order it returns them.
+pub const CAMPOS_PARCIALES: [Campo; 4] = [
+    Campo::NombreDelEstudiante,
+    Campo::Identificacion,
+    Campo::NumeroSolicitud,
+    Campo::FechaDeSolicitud,
+];
+
+impl SinProcesar {
+    /// Best-effort extraction of the common fields: student name, ID, request
+    /// number and date, empty when they can't be found.
+    pub fn campos_parciales(&self) -> [String; 4] {
+        let grupo = |re: &Regex, i: usize| {
+            re.captures(&self.texto)
+                .and_then(|c| c.get(i))
+                .map_or(String::new(), |m| m.as_str().trim().to_string())
+        };
+        [
+            grupo(&NOMBRE_PARCIAL_RE, 1),
+            sin_espacios(&grupo(&IDENTIFICACION_PARCIAL_RE, 1)),
+            sin_espacios(&grupo(&NUMERO_FECHA_PARCIAL_RE, 1)),
+            sin_espacios(&grupo(&NUMERO_FECHA_PARCIAL_RE, 2)),
+        ]
+    }
+
+    /// Builds a request of type `tipo` from the fields a reviewer read in the
+    /// chunk. Those that differ from what `campos_parciales` found are kept
+    /// as corrections, so the workbook shows what was typed by hand.
+    pub fn resolver(&self, tipo: &str, valores: &[(Campo, String)]) -> Result<Solicitud, String> {
+        let mut sol = Solicitud {
+            texto_fuente: self.texto.trim().to_string(),
+            paginas: self.paginas,
+            origenes: vec![self.origen],
+            ..Default::default()
+        };
+        sol.ajustar_a_tipo(tipo);
+        let mut encontrados = Vec::new();
+        for (campo, valor) in CAMPOS_PARCIALES.into_iter().zip(self.campos_parciales()) {
+            if !valor.is_empty() && sol.asignar(campo, &valor).is_ok() {
+                encontrados.push(campo);
+            }
+        }
+        for campo in CAMPOS_PARCIALES {
+            let vacio = valores
+                .iter()
+                .find(|(c, _)| *c == campo)
+                .is_none_or(|(_, valor)| valor.trim().is_empty());
+            if vacio {
+                return Err(trf("Falta el campo '{}'", &[&campo.encabezado()]));
+            }
+        }
+        for (campo, valor) in valores {
+            if sol.valor(*campo).unwrap_or_default() == valor.trim() {
+                continue;
+            }
+            if encontrados.contains(campo) {
+                sol.corregir(*campo, valor)?;
+            } else {
+                sol.asignar(*campo, valor)?;
+            }
+        }
+        Ok(sol)
+    }
+}
+
+pub fn read_and_extract_data(
+    texto: &str,
+    inicios_de_pagina: &[usize],
+    mut traza: Option<&mut Traza>,
+) -> Result<(SolicitudMap, Vec<SinProcesar>), String> {
+    if let Some(traza) = traza.as_deref_mut() {
+        traza.texto(texto, inicios_de_pagina);
+    }
+    // Split anotaciones from the rest of sections. Extracted text pasted by
+    // hand may not have them at all.
+    let (pdf_contents, anotaciones) = texto.split_once(ANOTACIONES_SEP).unwrap_or((texto, ""));
 
     // Separate pdf by ID | sections
     let sections: Vec<&str> = ID_SECTIONS_RE.split(pdf_contents).collect();
+    if let Some(traza) = traza.as_deref_mut() {
+        traza.secciones(&sections);
+        traza.anotaciones(anotaciones);
+    }
 
     // Split each section into chunks, each starting with "SOLICITUD ESTUDIANTE"
     let mut chunks: VecDeque<&str> = VecDeque::new();
@@ -171,6 +730,55 @@ pub fn read_and_extract_data(
         .filter(|s| !s.is_empty())
         .collect::<Vec<&str>>();
 
+    let reglas = reglas::activas();
+    clasificar_chunks(
+        texto,
+        &chunks,
+        inicios_de_pagina,
+        anotaciones,
+        traza,
+        &reglas,
+    )
+}
+
+/// Parses a dump written by `write_unhandled`, so old failures can be
+/// recovered once the rules are fixed.
+pub fn read_unhandled_dump(
+    texto: &str,
+    mut traza: Option<&mut Traza>,
+) -> Result<(SolicitudMap, Vec<SinProcesar>), String> {
+    if let Some(traza) = traza.as_deref_mut() {
+        traza.texto(texto, &[]);
+    }
+    let chunks = texto
+        .split(DUMP_SEP)
+        .map(str::trim)
+        .filter(|s| !s.is_empty())
+        .collect::<Vec<&str>>();
+    let reglas = reglas::activas();
+    clasificar_chunks(texto, &chunks, &[], "", traza, &reglas)
+}
+
+/// Parses a single chunk with `reglas` instead of the active rules, to try
+/// rules out before using them.
+pub fn probar_reglas(
+    chunk: &str,
+    reglas: &Reglas,
+) -> Result<(SolicitudMap, Vec<SinProcesar>), String> {
+    clasificar_chunks(chunk, &[chunk], &[], "", None, reglas)
+}
+
+/// Runs every chunk through the request regexes, sorting them by type.
+fn clasificar_chunks(
+    texto: &str,
+    chunks: &[&str],
+    inicios_de_pagina: &[usize],
+    anotaciones: &str,
+    mut traza: Option<&mut Traza>,
+    reglas: &Reglas,
+) -> Result<(SolicitudMap, Vec<SinProcesar>), String> {
+    let mut unhandled: Vec<SinProcesar> = Vec::new();
+
     // Simplified regex for a single solicitud block
     //let rs_re = r"(?s)nombre del estudiante\s*(.+?)\s*identificación\s*(\d+\s*\d*)\s*plan de estudios\s*(.+?)\s*número y fecha de la solicitud\s*([^ ]+)\s+(\d\s*\d/\d{2}/\d{4}|\d{2}/\d{2}/\d{2})\s*";
 
@@ -180,44 +788,34 @@ pub fn read_and_extract_data(
     let mut registro_trabajo_grado: Vec<Solicitud> = Vec::new();
     let mut autorizacion_menor_carga_minima: Vec<Solicitud> = Vec::new();
 
-    for chunk in chunks.iter() {
-        if let Some(captures) = SOLICITUD_CEA.captures(chunk) {
-            let nombre_del_estudiante = captures
-                .get(1)
-                .map_or("", |m| m.as_str())
-                .trim()
-                .to_string();
-            let identificacion = captures
-                .get(2)
-                .map_or("", |m| m.as_str())
-                .split_whitespace()
-                .collect::<Vec<&str>>()
-                .join("")
-                .parse()
-                .expect("Error parsing identificacion");
-            let plan_de_estudios = captures.get(3).map_or("", |m| m.as_str()).trim().into();
-            let numero_solicitud = captures
-                .get(4)
-                .map_or("", |m| m.as_str())
-                .split_whitespace()
-                .collect::<Vec<&str>>()
-                .join("");
-            let n_sol = numero_solicitud.clone();
-            let fecha_de_solicitud_str = captures
-                .get(5)
-                .map_or("", |m| m.as_str())
-                .split_whitespace()
-                .collect::<Vec<&str>>()
-                .join("");
-            let fecha_de_solicitud = match parse_date(&fecha_de_solicitud_str) {
-                Ok(date) => date,
-                Err(e) => {
-                    eprintln!("Error parsing date '{fecha_de_solicitud_str}': {e}");
-                    return Err(format!(
-                        "Error parsing date '{fecha_de_solicitud_str}': {e}"
-                    ));
+    for (posicion, chunk) in chunks.iter().enumerate() {
+        let paginas = paginas_de(texto, chunk, inicios_de_pagina);
+        if let Some(traza) = traza.as_deref_mut() {
+            traza.chunk(posicion + 1, chunk, paginas);
+        }
+        let sin_procesar = |motivo: String| {
+            log::warn!("{motivo}:\n{chunk}");
+            SinProcesar {
+                posicion: posicion + 1,
+                texto: chunk.to_string(),
+                paginas,
+                motivo,
+                origen: 0,
+            }
+        };
+
+        if let Some(captures) = reglas.regex(Regla::Cea).captures(chunk) {
+            if let Some(traza) = traza.as_deref_mut() {
+                traza.coincidencia(Regla::Cea.nombre(), &captures);
+            }
+            let base = match solicitud_base(&captures, chunk, paginas, true) {
+                Ok(base) => base,
+                Err(motivo) => {
+                    unhandled.push(sin_procesar(motivo));
+                    continue;
                 }
             };
+            let n_sol = base.numero_solicitud.clone();
 
             let periodo: Option<String> = None;
             let motivos = Some(
@@ -228,73 +826,39 @@ pub fn read_and_extract_data(
                     .to_string(),
             );
             // solicitud empieza con CEA so far
-            let _anexos = Some(
-                captures
-                    .get(7)
-                    .map_or("", |m| m.as_str())
-                    .trim()
-                    .to_string(),
-            );
             let last_field_capture = captures.get(8).map_or("", |m| m.as_str()).trim();
             let adjuntos = Some(DOC_ANEX_DOC_RE.find_iter(last_field_capture).count());
             let second = DOC_ANEX_DOC_RE.replace(last_field_capture, "");
             let materias = Some(second.to_string());
             let solicitud = Solicitud {
-                nombre_del_estudiante,
-                plan_de_estudios,
-                numero_solicitud,
-                fecha_de_solicitud,
-                identificacion,
                 motivos,
                 adjuntos,
                 materias,
                 periodo,
+                ..base
             };
             if n_sol.starts_with("CEAP") {
                 cancelacion_extemporanea_asignaturas_posgrado.push(solicitud)
             } else if n_sol.starts_with("CEA") {
                 cancelaciones_extemporanea_asignaturas.push(solicitud)
             } else {
-                println!("Warning: Eh? QUE ES ESTO?????");
-                println!("{chunk}")
+                unhandled.push(sin_procesar(trf(
+                    "Número de solicitud '{}' no corresponde a una cancelación de asignaturas",
+                    &[&n_sol],
+                )));
+            }
+        } else if let Some(captures) = reglas.regex(Regla::Cs).captures(chunk) {
+            if let Some(traza) = traza.as_deref_mut() {
+                traza.coincidencia(Regla::Cs.nombre(), &captures);
             }
-        } else if let Some(captures) = SOLICITUD_CS.captures(chunk) {
-            let nombre_del_estudiante = captures
-                .get(1)
-                .map_or("", |m| m.as_str())
-                .trim()
-                .to_string();
-            let identificacion = captures
-                .get(2)
-                .map_or("", |m| m.as_str())
-                .split_whitespace()
-                .collect::<Vec<&str>>()
-                .join("")
-                .parse()
-                .expect("Error parsing identificacion");
-            let plan_de_estudios = captures.get(3).map_or("", |m| m.as_str()).trim().into();
-            let numero_solicitud = captures
-                .get(4)
-                .map_or("", |m| m.as_str())
-                .split_whitespace()
-                .collect::<Vec<&str>>()
-                .join("");
-            let n_sol = numero_solicitud.clone();
-            let fecha_de_solicitud_str = captures
-                .get(5)
-                .map_or("", |m| m.as_str())
-                .split_whitespace()
-                .collect::<Vec<&str>>()
-                .join("");
-            let fecha_de_solicitud = match parse_date(&fecha_de_solicitud_str) {
-                Ok(date) => date,
-                Err(e) => {
-                    eprintln!("Error parsing date '{fecha_de_solicitud_str}': {e}");
-                    return Err(format!(
-                        "Error parsing date '{fecha_de_solicitud_str}': {e}"
-                    ));
+            let base = match solicitud_base(&captures, chunk, paginas, true) {
+                Ok(base) => base,
+                Err(motivo) => {
+                    unhandled.push(sin_procesar(motivo));
+                    continue;
                 }
             };
+            let n_sol = base.numero_solicitud.clone();
 
             let motivos = Some(
                 captures
@@ -303,13 +867,6 @@ pub fn read_and_extract_data(
                     .trim()
                     .to_string(),
             );
-            let _anexos = Some(
-                captures
-                    .get(7)
-                    .map_or("", |m| m.as_str())
-                    .trim()
-                    .to_string(),
-            );
             let materias = None;
             let last_field_capture = captures.get(8).map_or("", |m| m.as_str()).trim();
             let adjuntos = Some(DOC_ANEX_DOC_RE.find_iter(last_field_capture).count());
@@ -317,60 +874,34 @@ pub fn read_and_extract_data(
             let periodo = Some(second.to_string());
 
             let solicitud = Solicitud {
-                nombre_del_estudiante,
-                plan_de_estudios,
-                numero_solicitud,
-                fecha_de_solicitud,
-                identificacion,
                 motivos,
                 adjuntos,
                 materias,
                 periodo,
+                ..base
             };
             if n_sol.starts_with("CS") {
                 cancelaciones_semestre.push(solicitud);
             } else if n_sol.starts_with("ACM") {
                 autorizacion_menor_carga_minima.push(solicitud);
             } else {
-                dbg!(captures.get(0));
+                unhandled.push(sin_procesar(trf(
+                    "Número de solicitud '{}' no corresponde a una cancelación de semestre",
+                    &[&n_sol],
+                )));
             }
-        } else if let Some(captures) = SOLICITUD_ACM.captures(chunk) {
-            let nombre_del_estudiante = captures
-                .get(1)
-                .map_or("", |m| m.as_str())
-                .trim()
-                .to_string();
-            let identificacion = captures
-                .get(2)
-                .map_or("", |m| m.as_str())
-                .split_whitespace()
-                .collect::<Vec<&str>>()
-                .join("")
-                .parse()
-                .expect("Error parsing identificacion");
-            let plan_de_estudios = captures.get(3).map_or("", |m| m.as_str()).trim().into();
-            let numero_solicitud = captures
-                .get(4)
-                .map_or("", |m| m.as_str())
-                .split_whitespace()
-                .collect::<Vec<&str>>()
-                .join("");
-            let n_sol = numero_solicitud.clone();
-            let fecha_de_solicitud_str = captures
-                .get(5)
-                .map_or("", |m| m.as_str())
-                .split_whitespace()
-                .collect::<Vec<&str>>()
-                .join("");
-            let fecha_de_solicitud = match parse_date(&fecha_de_solicitud_str) {
-                Ok(date) => date,
-                Err(e) => {
-                    eprintln!("Error parsing date '{fecha_de_solicitud_str}': {e}");
-                    return Err(format!(
-                        "Error parsing date '{fecha_de_solicitud_str}': {e}"
-                    ));
+        } else if let Some(captures) = reglas.regex(Regla::Acm).captures(chunk) {
+            if let Some(traza) = traza.as_deref_mut() {
+                traza.coincidencia(Regla::Acm.nombre(), &captures);
+            }
+            let base = match solicitud_base(&captures, chunk, paginas, true) {
+                Ok(base) => base,
+                Err(motivo) => {
+                    unhandled.push(sin_procesar(motivo));
+                    continue;
                 }
             };
+            let n_sol = base.numero_solicitud.clone();
 
             let motivos = Some(
                 captures
@@ -379,13 +910,6 @@ pub fn read_and_extract_data(
                     .trim()
                     .to_string(),
             );
-            let _anexos = Some(
-                captures
-                    .get(7)
-                    .map_or("", |m| m.as_str())
-                    .trim()
-                    .to_string(),
-            );
             let materias = None;
             let last_field_capture = captures.get(8).map_or("", |m| m.as_str()).trim();
             let adjuntos = Some(DOC_ANEX_DOC_RE.find_iter(last_field_capture).count());
@@ -393,88 +917,66 @@ pub fn read_and_extract_data(
             let periodo = Some(second.to_string());
 
             let solicitud = Solicitud {
-                nombre_del_estudiante,
-                plan_de_estudios,
-                numero_solicitud,
-                fecha_de_solicitud,
-                identificacion,
                 motivos,
                 adjuntos,
                 materias,
                 periodo,
+                ..base
             };
 
             if n_sol.starts_with("ACM") {
                 autorizacion_menor_carga_minima.push(solicitud);
             } else {
-                dbg!(captures.get(0));
+                unhandled.push(sin_procesar(trf(
+                    "Número de solicitud '{}' no corresponde a una autorización de carga mínima",
+                    &[&n_sol],
+                )));
             }
-        } else if let Some(captures) = SOLICITUD_RTG.captures(chunk) {
-            let nombre_del_estudiante = captures
-                .get(1)
-                .map_or("", |m| m.as_str())
-                .trim()
-                .to_string();
-            let identificacion = captures
-                .get(2)
-                .map_or("", |m| m.as_str())
-                .split_whitespace()
-                .collect::<Vec<&str>>()
-                .join("")
-                .parse()
-                .expect("Error parsing identificacion");
-            let plan_de_estudios = captures.get(3).map_or("", |m| m.as_str()).trim().into();
-            let numero_solicitud = captures
-                .get(4)
-                .map_or("", |m| m.as_str())
-                .trim()
-                .to_string();
-            let n_sol = numero_solicitud.clone();
-            let fecha_de_solicitud_str = captures
-                .get(5)
-                .map_or("", |m| m.as_str())
-                .split_whitespace()
-                .collect::<Vec<&str>>()
-                .join("");
-            let fecha_de_solicitud = match parse_date(&fecha_de_solicitud_str) {
-                Ok(date) => date,
-                Err(e) => {
-                    eprintln!("Error parsing date '{fecha_de_solicitud_str}': {e}");
-                    return Err(format!(
-                        "Error parsing date '{fecha_de_solicitud_str}': {e}"
-                    ));
+        } else if let Some(captures) = reglas.regex(Regla::Rtg).captures(chunk) {
+            if let Some(traza) = traza.as_deref_mut() {
+                traza.coincidencia(Regla::Rtg.nombre(), &captures);
+            }
+            let base = match solicitud_base(&captures, chunk, paginas, false) {
+                Ok(base) => base,
+                Err(motivo) => {
+                    unhandled.push(sin_procesar(motivo));
+                    continue;
                 }
             };
-            let motivos = None;
-            let materias = None;
+            let n_sol = base.numero_solicitud.clone();
 
             let last_field_capture = captures.get(6).map_or("", |m| m.as_str());
-
             let adjuntos = Some(DOC_ANEX_DOC_RE.find_iter(last_field_capture).count());
 
-            let _second = DOC_ANEX_DOC_RE.replace(last_field_capture, "");
-            let periodo = None;
             let solicitud = Solicitud {
-                nombre_del_estudiante,
-                plan_de_estudios,
-                numero_solicitud,
-                fecha_de_solicitud,
-                identificacion,
-                motivos,
+                motivos: None,
                 adjuntos,
-                materias,
-                periodo,
+                materias: None,
+                periodo: None,
+                ..base
             };
             if n_sol.starts_with("RTG") {
                 registro_trabajo_grado.push(solicitud);
+            } else {
+                unhandled.push(sin_procesar(trf(
+                    "Número de solicitud '{}' no corresponde a un registro de trabajo de grado",
+                    &[&n_sol],
+                )));
             }
         } else {
-            eprintln!("Warning: Could not parse solicitud in chunk:\n{chunk}");
-            unhandled.push(chunk);
-            eprintln!("--------------------");
+            if let Some(traza) = traza.as_deref_mut() {
+                traza.sin_coincidencia();
+            }
+            unhandled.push(sin_procesar(
+                tr("Ningún formato de solicitud conocido coincide con el texto").to_string(),
+            ));
         }
     }
 
+    if let Some(traza) = traza {
+        traza.sin_procesar(&unhandled);
+    }
+
     let mut solicitudes: SolicitudMap = HashMap::new();
 
     if !cancelacion_extemporanea_asignaturas_posgrado.is_empty() {
@@ -497,6 +999,7 @@ pub fn read_and_extract_data(
     if !anotaciones.is_empty() {
         let bs = Solicitud {
             motivos: Some(anotaciones.to_string()),
+            origenes: vec![0],
             ..Default::default()
         };
         solicitudes.insert(ANOTACIONES.to_string(), vec![bs]);
@@ -505,107 +1008,837 @@ pub fn read_and_extract_data(
     Ok((solicitudes, unhandled))
 }
 
-pub fn write_data_to_excel(
-    data: &HashMap<String, Vec<Solicitud>>,
-    excel_path: &PathBuf,
-) -> Result<(), XlsxError> {
+pub fn write_data_to_excel(reporte: &Reporte, excel_path: &PathBuf) -> Result<(), XlsxError> {
+    let data = &reporte.solicitudes;
+    let consolidado = reporte.procedencias.len() > 1;
     let mut workbook = Workbook::new();
     let mut ordered_data: Vec<(&String, &Vec<Solicitud>)> = data.iter().collect();
     ordered_data.sort_by(|a, b| a.0.cmp(b.0));
+    let falta_format = Format::new().set_background_color(COLOR_FALTA);
+    let sospechoso_format = Format::new().set_background_color(COLOR_SOSPECHOSO);
+    let corregido_format = Format::new().set_background_color(COLOR_CORREGIDO);
     for (sheet_name, sheet_data) in ordered_data {
-        let worksheet = workbook.add_worksheet().set_name(sheet_name)?;
+        let worksheet = workbook.add_worksheet().set_name(tr(sheet_name))?;
 
         // Add a bold format for the headers.
         let bold_format = Format::new().set_bold();
 
         // Write the headers.
-        worksheet.write_string_with_format(0, 0, "Nombre del estudiante", &bold_format)?;
-        worksheet.write_string_with_format(0, 1, "Plan de estudios", &bold_format)?;
-        worksheet.write_string_with_format(0, 2, "Número de solicitud", &bold_format)?;
-        worksheet.write_string_with_format(0, 3, "Fecha de solicitud", &bold_format)?;
-        worksheet.write_string_with_format(0, 4, "Identificación", &bold_format)?;
-        let mut j = 5;
-        if sheet_name.starts_with(CEA) {
-            worksheet.write_string_with_format(0, j, "Adjuntos", &bold_format)?;
-            j += 1;
-            worksheet.write_string_with_format(0, j, "Materias", &bold_format)?;
-            j += 1;
-        } else if sheet_name.starts_with(CS) || sheet_name.starts_with(ACM) {
-            worksheet.write_string_with_format(0, j, "Adjuntos", &bold_format)?;
+        let campos = campos_del_tipo(sheet_name);
+        for (col, campo) in campos.iter().enumerate() {
+            worksheet.write_string_with_format(0, col as u16, campo.encabezado(), &bold_format)?;
+        }
+        let mut j = campos.len() as u16;
+        let col_origen = j;
+        if consolidado {
+            worksheet.write_string_with_format(
+                0,
+                col_origen,
+                tr(REPORTE_DE_ORIGEN),
+                &bold_format,
+            )?;
             j += 1;
-            worksheet.write_string_with_format(0, j, "Periodo", &bold_format)?;
+        }
+        let col_pdf = j;
+        let con_paginas = campos.contains(&Campo::Paginas);
+        if con_paginas {
+            worksheet.write_string_with_format(0, col_pdf, tr(PDF), &bold_format)?;
             j += 1;
         }
-        if !sheet_name.starts_with(RTG) {
-            worksheet.write_string_with_format(0, j, "Motivos", &bold_format)?;
+        // Empty columns for the reviewer, carried over between runs.
+        let col_decision = j;
+        if sheet_name.as_str() != ANOTACIONES {
+            worksheet.write_string_with_format(0, col_decision, tr(DECISION), &bold_format)?;
+            worksheet.write_string_with_format(0, col_decision + 1, tr(NOTAS), &bold_format)?;
         }
 
-        // Write data rows
+        // Write data rows, coloring the cells the reviewer should double check.
+        // Anotaciones is free text, so there is nothing to check there.
+        let revisar = sheet_name.as_str() != ANOTACIONES;
         for (row, sol) in sheet_data.iter().enumerate() {
-            for (col, field) in sol.iter().enumerate() {
-                worksheet.write_string(row as u32 + 1, col as u16, field)?;
+            for (col, (campo, field)) in sol.iter().enumerate() {
+                let row = row as u32 + 1;
+                let col = col as u16;
+                if let Some(original) = sol.originales.get(&campo) {
+                    worksheet.write_string_with_format(row, col, field, &corregido_format)?;
+                    worksheet.insert_note(row, col, &nota_original(original))?;
+                    continue;
+                }
+                match campo.revisar(&field).filter(|_| revisar) {
+                    Some(Revision::Falta) => {
+                        worksheet.write_string_with_format(row, col, field, &falta_format)?
+                    }
+                    Some(Revision::Sospechoso) => {
+                        worksheet.write_string_with_format(row, col, field, &sospechoso_format)?
+                    }
+                    None => worksheet.write_string(row, col, field)?,
+                };
+                if campo == Campo::NumeroSolicitud && !sol.texto_fuente.is_empty() {
+                    worksheet.insert_note(row, col, &nota_fuente(&sol.texto_fuente))?;
+                }
+            }
+            if consolidado {
+                worksheet.write_string(
+                    row as u32 + 1,
+                    col_origen,
+                    reporte.nombres(&sol.origenes),
+                )?;
+            }
+            if con_paginas && let Some((primera, _)) = sol.paginas {
+                let pdf_path = reporte.pdf_path(sol.origenes.first().copied());
+                worksheet.write_url(row as u32 + 1, col_pdf, enlace_pdf(pdf_path, primera))?;
+            }
+            if !sol.decision.is_empty() {
+                worksheet.write_string(row as u32 + 1, col_decision, &sol.decision)?;
+            }
+            if !sol.notas.is_empty() {
+                worksheet.write_string(row as u32 + 1, col_decision + 1, &sol.notas)?;
             }
         }
     }
 
+    if !reporte.sin_procesar.is_empty() {
+        write_sin_procesar(&mut workbook, reporte)?;
+    }
+    if !reporte.conflictos.is_empty() {
+        revisiones::write_conflictos(&mut workbook, &reporte.conflictos)?;
+    }
+    write_leyenda(
+        &mut workbook,
+        &falta_format,
+        &sospechoso_format,
+        &corregido_format,
+    )?;
+    revisiones::write_originales(&mut workbook, data)?;
+    write_procedencia(&mut workbook, &reporte.procedencias)?;
+
     workbook.save(excel_path)?;
 
     Ok(())
 }
 
-fn write_unhandled(txt_path: PathBuf, unhandled: Vec<&str>) -> std::io::Result<()> {
-    // Create or open the file for writing.
-    let mut file = File::create(txt_path)?;
+/// `file://` URI of `pdf_path` for a cell link. Excel keeps anything after `#`
+/// as a place inside the target and never hands it to the PDF viewer, so the
+/// link can only open the file; the page is written in its own cell instead.
+///
+/// rust_xlsxwriter drops the `file:///` prefix and stores what is left as a
+/// relative path unless it starts like `C:` or `\\`, so Unix paths keep their
+/// leading `/` (resolving to the same file from the workbook) and shares keep
+/// their backslashes, as Excel writes them.
+fn uri_archivo(pdf_path: &Path) -> String {
+    let pdf_path = std::path::absolute(pdf_path).unwrap_or_else(|_| pdf_path.to_path_buf());
+    uri_de(&pdf_path.to_string_lossy())
+}
 
-    // Iterate through the lines and write each one to the file, followed by a newline.
-    for line in unhandled {
-        writeln!(file, "{line}")?;
-        writeln!(file, "--------------------------------")?;
+fn uri_de(absoluta: &str) -> String {
+    if absoluta.starts_with('/') || absoluta.starts_with(r"\\") {
+        format!("file:///{absoluta}")
+    } else {
+        format!("file:///{}", absoluta.replace('\\', "/"))
     }
+}
+
+/// Link to the PDF of a request, telling on which page to look.
+fn enlace_pdf(pdf_path: &Path, pagina: usize) -> Url {
+    Url::new(uri_archivo(pdf_path))
+        .set_text(tr("Abrir"))
+        .set_tip(trf("Abrir el PDF, está en la página {}", &[&pagina]))
+}
+
+// Excel limits cells and notes to 32767 characters, minus some room for the
+// author prefix in notes.
+const LARGO_MAXIMO_CELDA: usize = 32_000;
+
+fn truncar(texto: &str) -> String {
+    if texto.chars().count() > LARGO_MAXIMO_CELDA {
+        texto
+            .chars()
+            .take(LARGO_MAXIMO_CELDA)
+            .chain("…".chars())
+            .collect()
+    } else {
+        texto.to_string()
+    }
+}
+
+/// Note with the original report text, attached to the request number cell.
+fn nota_fuente(texto_fuente: &str) -> Note {
+    Note::new(truncar(texto_fuente))
+        .add_author_prefix(false)
+        .set_width(480)
+        .set_height(320)
+}
+
+/// Note on a corrected cell with what the PDF said.
+fn nota_original(original: &str) -> Note {
+    Note::new(trf("Valor en el PDF: {}", &[&truncar(original)])).add_author_prefix(false)
+}
+
+pub const SIN_PROCESAR: &str = "Sin procesar";
+
+/// Lists the chunks no rule could handle, with whatever fields could be salvaged.
+fn write_sin_procesar(workbook: &mut Workbook, reporte: &Reporte) -> Result<(), XlsxError> {
+    let consolidado = reporte.procedencias.len() > 1;
+    let worksheet = workbook.add_worksheet().set_name(tr(SIN_PROCESAR))?;
+    let bold_format = Format::new().set_bold();
+    let texto_format = Format::new().set_text_wrap();
+
+    let headers = [
+        "Posición",
+        "Página(s)",
+        "Motivo",
+        "Nombre del estudiante",
+        "Identificación",
+        "Número de solicitud",
+        "Fecha de solicitud",
+        "Texto",
+    ];
+    for (col, header) in headers.iter().enumerate() {
+        worksheet.write_string_with_format(0, col as u16, tr(header), &bold_format)?;
+    }
+    let mut col_pdf = headers.len() as u16;
+    if consolidado {
+        worksheet.write_string_with_format(0, col_pdf, tr(REPORTE_DE_ORIGEN), &bold_format)?;
+        col_pdf += 1;
+    }
+    worksheet.write_string_with_format(0, col_pdf, tr(PDF), &bold_format)?;
+
+    for (row, chunk) in reporte.sin_procesar.iter().enumerate() {
+        let pdf_path = reporte.pdf_path(Some(chunk.origen));
+        let row = row as u32 + 1;
+        worksheet.write_number(row, 0, chunk.posicion as f64)?;
+        if let Some((primera, ultima)) = chunk.paginas {
+            let texto = if primera == ultima {
+                primera.to_string()
+            } else {
+                format!("{primera}-{ultima}")
+            };
+            worksheet.write_string(row, 1, texto)?;
+            worksheet.write_url(row, col_pdf, enlace_pdf(pdf_path, primera))?;
+        }
+        worksheet.write_string(row, 2, &chunk.motivo)?;
+        for (col, campo) in chunk.campos_parciales().iter().enumerate() {
+            worksheet.write_string(row, col as u16 + 3, campo)?;
+        }
+        worksheet.write_string_with_format(row, 7, truncar(chunk.texto.trim()), &texto_format)?;
+        if consolidado {
+            worksheet.write_string(row, 8, reporte.nombres(&[chunk.origen]))?;
+        }
+    }
+    worksheet.set_column_width(2, 40)?;
+    worksheet.set_column_width(7, 100)?;
 
-    // The file is automatically closed when `file` goes out of scope.
     Ok(())
 }
 
-pub fn process_pdf(pdf_path: PathBuf) -> Result<HashMap<String, Vec<Solicitud>>, String> {
-    // Generate output paths
-    let file_stem = pdf_path.file_stem().unwrap().to_string_lossy().to_string();
-    let excel_path = pdf_path
-        .parent()
-        .unwrap()
-        .join(format!("{file_stem}.xlsx"));
-    let excel_name = excel_path
-        .file_name()
-        .unwrap()
+const METADATOS: &str = "Metadatos";
+
+/// Records the provenance both in the document properties and in a hidden sheet.
+/// With several sources every property is numbered, one block per report.
+pub fn write_procedencia(
+    workbook: &mut Workbook,
+    procedencias: &[Procedencia],
+) -> Result<(), XlsxError> {
+    let archivos = procedencias
+        .iter()
+        .map(|p| format!("{} (SHA-256 {})", p.archivo(), p.sha256))
+        .collect::<Vec<String>>()
+        .join(", ");
+    let titulo = match procedencias {
+        [procedencia] => trf("Reporte de agenda {}", &[&procedencia.archivo()]),
+        _ => tr("Reporte de agenda consolidado").to_string(),
+    };
+    let propiedades: Vec<(String, String)> = procedencias
+        .iter()
+        .enumerate()
+        .flat_map(|(i, procedencia)| {
+            procedencia
+                .propiedades()
+                .into_iter()
+                .map(move |(nombre, valor)| {
+                    if procedencias.len() > 1 {
+                        (format!("{} {}", tr(nombre), i + 1), valor)
+                    } else {
+                        (tr(nombre).to_string(), valor)
+                    }
+                })
+        })
+        .collect();
+
+    let mut properties = DocProperties::new()
+        .set_title(titulo)
+        .set_author(format!("reps-sia {}", env!("CARGO_PKG_VERSION")))
+        .set_comment(trf("Generado a partir de {}", &[&archivos]));
+    for (nombre, valor) in &propiedades {
+        properties = properties.set_custom_property(nombre, valor.as_str());
+    }
+    workbook.set_properties(&properties);
+
+    let worksheet = workbook.add_worksheet().set_name(tr(METADATOS))?;
+    let bold_format = Format::new().set_bold();
+    for (row, (nombre, valor)) in propiedades.iter().enumerate() {
+        worksheet.write_string_with_format(row as u32, 0, nombre, &bold_format)?;
+        worksheet.write_string(row as u32, 1, valor)?;
+    }
+    worksheet.set_column_width(0, 22)?;
+    worksheet.set_column_width(1, 70)?;
+    worksheet.set_hidden(true);
+
+    Ok(())
+}
+
+const LEYENDA: &str = "Leyenda";
+const COLOR_FALTA: Color = Color::RGB(0xFFC7CE);
+const COLOR_SOSPECHOSO: Color = Color::RGB(0xFFEB9C);
+const COLOR_CORREGIDO: Color = Color::RGB(0xBDD7EE);
+pub const DECISION: &str = "Decisión";
+pub const NOTAS: &str = "Notas";
+
+/// Adds a sheet explaining what each highlight color means.
+fn write_leyenda(
+    workbook: &mut Workbook,
+    falta_format: &Format,
+    sospechoso_format: &Format,
+    corregido_format: &Format,
+) -> Result<(), XlsxError> {
+    let worksheet = workbook.add_worksheet().set_name(tr(LEYENDA))?;
+    let bold_format = Format::new().set_bold();
+
+    worksheet.write_string_with_format(0, 0, tr("Color"), &bold_format)?;
+    worksheet.write_string_with_format(0, 1, tr("Significado"), &bold_format)?;
+    worksheet.write_string_with_format(1, 0, tr("Falta"), falta_format)?;
+    worksheet.write_string(1, 1, tr("Campo obligatorio vacío"))?;
+    worksheet.write_string_with_format(2, 0, tr("Sospechoso"), sospechoso_format)?;
+    worksheet.write_string(
+        2,
+        1,
+        tr("Campo demasiado largo o con texto de otra sección del reporte, verificar contra el PDF"),
+    )?;
+    worksheet.write_string(3, 0, tr("Nota"))?;
+    worksheet.write_string(
+        3,
+        1,
+        tr("El número de solicitud tiene una nota con el texto original del reporte"),
+    )?;
+    worksheet.write_string(4, 0, tr(SIN_PROCESAR))?;
+    worksheet.write_string(
+        4,
+        1,
+        tr("Fragmentos del reporte que no se pudieron procesar, con el motivo y los datos rescatables"),
+    )?;
+    worksheet.write_string_with_format(5, 0, tr("Corregido"), corregido_format)?;
+    worksheet.write_string(5, 1, tr("Valor corregido por un revisor o por el archivo de correcciones, la nota tiene el valor del PDF"))?;
+    worksheet.set_column_width(1, 80)?;
+
+    Ok(())
+}
+
+/// Returns `path` if nothing exists there, otherwise the first free `stem (n).ext`.
+pub fn ruta_libre(path: PathBuf) -> PathBuf {
+    ruta_libre_con(path, |candidate| candidate.exists())
+}
+
+/// Like `ruta_libre`, with `ocupada` deciding which paths are taken.
+pub fn ruta_libre_con(path: PathBuf, ocupada: impl Fn(&Path) -> bool) -> PathBuf {
+    if !ocupada(&path) {
+        return path;
+    }
+    let stem = path
+        .file_stem()
+        .unwrap_or_default()
         .to_string_lossy()
         .to_string();
+    let extension = path.extension().map(|e| e.to_string_lossy().to_string());
+    (1..)
+        .map(|n| {
+            let name = match &extension {
+                Some(extension) => format!("{stem} ({n}).{extension}"),
+                None => format!("{stem} ({n})"),
+            };
+            path.with_file_name(name)
+        })
+        .find(|candidate| !ocupada(candidate))
+        .expect("some numbered file name is free")
+}
 
-    let txt_path = pdf_path
-        .parent()
-        .unwrap()
-        .join(format!("{file_stem}.txt"));
+/// Line between chunks in the unhandled dump.
+const DUMP_SEP: &str = "--------------------------------";
 
-    if let Some(output_path) = FileDialog::new()
-        .add_filter("Excel", &["xlsx"])
-        .set_file_name(excel_name)
-        .save_file()
-    {
-        // Your existing processing logic should be integrated here
-        let bytes = std::fs::read(pdf_path).expect("Error reading PDF file crate");
-        let out = pdf_extract::extract_text_from_mem(&bytes)
-            .expect("Error extracting text from PDF crate");
-
-        // Extract data from text
-        let (data, unhandled) = read_and_extract_data(&out)?;
-        if !unhandled.is_empty() {
-            write_unhandled(txt_path, unhandled).expect("Tried to write txt but failed");
-        }
+fn write_unhandled(txt_path: PathBuf, unhandled: &[SinProcesar]) -> std::io::Result<()> {
+    // Create the file for writing, never overwriting an older dump.
+    let mut file = File::create(ruta_libre(txt_path))?;
+
+    // Iterate through the lines and write each one to the file, followed by a newline.
+    for chunk in unhandled {
+        writeln!(file, "{}", chunk.texto)?;
+        writeln!(file, "{DUMP_SEP}")?;
+    }
+
+    // The file is automatically closed when `file` goes out of scope.
+    Ok(())
+}
+
+/// Reads and parses a single PDF.
+pub fn analizar_pdf(pdf_path: &Path, traza: Option<&mut Traza>) -> Result<Reporte, String> {
+    let bytes = std::fs::read(pdf_path)
+        .map_err(|e| format!("Error reading '{}': {e}", pdf_path.display()))?;
+    let procedencia = Procedencia::new(pdf_path, &bytes);
+    let (out, inicios_de_pagina) = extract_pages(&bytes)?;
+
+    // Extract data from text
+    let (solicitudes, sin_procesar) = read_and_extract_data(&out, &inicios_de_pagina, traza)?;
+    Ok(Reporte {
+        procedencias: vec![procedencia],
+        solicitudes,
+        sin_procesar,
+        conflictos: Vec::new(),
+    })
+}
+
+/// Reads and parses a text file, either text extracted from a report or a dump
+/// of unhandled chunks.
+pub fn analizar_txt(txt_path: &Path, traza: Option<&mut Traza>) -> Result<Reporte, String> {
+    let bytes = std::fs::read(txt_path)
+        .map_err(|e| format!("Error reading '{}': {e}", txt_path.display()))?;
+    let procedencia = Procedencia::new(txt_path, &bytes);
+    let texto = String::from_utf8_lossy(&bytes);
+
+    // Dumps only keep what came after each "SOLICITUD ESTUDIANTE" header.
+    let (solicitudes, sin_procesar) = if SOLICITUD_SECCION_RE.is_match(&texto) {
+        read_and_extract_data(&texto, &[], traza)?
+    } else {
+        read_unhandled_dump(&texto, traza)?
+    };
+    Ok(Reporte {
+        procedencias: vec![procedencia],
+        solicitudes,
+        sin_procesar,
+        conflictos: Vec::new(),
+    })
+}
+
+/// Parses a PDF report or, for `.txt` files, its text.
+pub fn analizar(path: &Path, traza: Option<&mut Traza>) -> Result<Reporte, String> {
+    let es_txt = path
+        .extension()
+        .is_some_and(|ext| ext.eq_ignore_ascii_case("txt"));
+    if es_txt {
+        analizar_txt(path, traza)
+    } else {
+        analizar_pdf(path, traza)
+    }
+}
+
+/// What to do with a report besides writing the xlsx.
+#[derive(Debug, Clone, Default)]
+pub struct Opciones {
+    /// Also dump the unhandled chunks to a txt next to each PDF.
+    pub escribir_txt: bool,
+    /// Workbook generated before whose reviewer edits are carried over.
+    pub libro_revisado: Option<PathBuf>,
+    /// Known fixes to apply; the one in the user config dir is used if unset.
+    pub correcciones: Option<PathBuf>,
+    /// Write a parse trace next to each report, for bug reports.
+    pub traza: bool,
+}
+
+/// Applies the options that change the parsed data before writing it.
+pub fn preparar(reporte: &mut Reporte, opciones: &Opciones) -> Result<(), String> {
+    if let Some(libro) = &opciones.libro_revisado {
+        LibroRevisado::leer(libro)?.aplicar(reporte);
+    }
+    // Known fixes go last so they win over whatever the reviewers left.
+    let ruta_correcciones = opciones
+        .correcciones
+        .clone()
+        .or_else(|| correcciones::ruta_predeterminada().filter(|path| path.exists()));
+    if let Some(path) = ruta_correcciones {
+        Correcciones::leer(&path)?.aplicar(reporte)?;
+    }
+    Ok(())
+}
+
+/// Dumps the unhandled chunks of a single-report `Reporte` to a txt next to its PDF.
+fn write_unhandled_txt(reporte: &Reporte) -> Result<(), String> {
+    if reporte.sin_procesar.is_empty() {
+        return Ok(());
+    }
+    let txt_path = reporte.pdf_path(None).with_extension("txt");
+    write_unhandled(txt_path, &reporte.sin_procesar)
+        .map_err(|e| format!("Failed to write txt file: {e}"))
+}
 
-        // Write data to Excel
-        write_data_to_excel(&data, &output_path)
-            .map_err(|e| format!("Failed to write Excel file: {e}"))?;
-        Ok(data)
+/// Parses a report with everything `opciones` asks for along the way: its
+/// trace and the txt with unhandled chunks.
+pub fn analizar_con_opciones(path: &Path, opciones: &Opciones) -> Result<Reporte, String> {
+    let _procesando = bitacora::procesando(path);
+    log::info!("Processing '{}'", path.display());
+    let reporte = if opciones.traza {
+        let mut traza = Traza::nueva(path);
+        let reporte = analizar(path, Some(&mut traza));
+        // The trace matters the most when parsing failed.
+        traza.escribir(&ruta_traza(path))?;
+        reporte?
     } else {
-        Err("No output file selected".to_string())
+        analizar(path, None)?
+    };
+    if opciones.escribir_txt {
+        write_unhandled_txt(&reporte)?;
+    }
+    Ok(reporte)
+}
+
+/// Applies `opciones` to a parsed report and writes it to `output_path`.
+pub fn exportar(
+    mut reporte: Reporte,
+    output_path: &PathBuf,
+    opciones: &Opciones,
+) -> Result<Reporte, String> {
+    preparar(&mut reporte, opciones)?;
+    escribir(&reporte, output_path)?;
+    Ok(reporte)
+}
+
+/// Writes an already prepared report, e.g. after it was reviewed in the preview.
+pub fn escribir(reporte: &Reporte, output_path: &PathBuf) -> Result<(), String> {
+    write_data_to_excel(reporte, output_path)
+        .map_err(|e| format!("Failed to write Excel file: {e}"))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::io::Read;
+
+    #[test]
+    fn revisar_marca_campos_vacios_como_faltantes() {
+        assert_eq!(
+            Campo::NombreDelEstudiante.revisar("  "),
+            Some(Revision::Falta)
+        );
+        assert_eq!(Campo::Motivos.revisar(""), Some(Revision::Falta));
+    }
+
+    #[test]
+    fn revisar_marca_campos_largos_o_con_etiquetas_como_sospechosos() {
+        assert_eq!(
+            Campo::PlanDeEstudios.revisar(&"a".repeat(201)),
+            Some(Revision::Sospechoso)
+        );
+        assert_eq!(Campo::Motivos.revisar(&"a".repeat(201)), None);
+        assert_eq!(
+            Campo::Motivos.revisar("Viaje. Materias relacionadas a la solicitud: Cálculo"),
+            Some(Revision::Sospechoso)
+        );
+    }
+
+    #[test]
+    fn revisar_acepta_valores_normales_y_no_revisa_adjuntos_ni_paginas() {
+        assert_eq!(Campo::NombreDelEstudiante.revisar("Ana Pérez"), None);
+        assert_eq!(Campo::Adjuntos.revisar(""), None);
+        assert_eq!(Campo::Paginas.revisar(""), None);
+    }
+
+    /// Report of `pdf` with one request per `(tipo, numero)`, and one unhandled chunk.
+    fn reporte(pdf: &str, solicitudes: &[(&str, &str)]) -> Reporte {
+        let mut reporte = Reporte {
+            procedencias: vec![Procedencia::new(Path::new(pdf), pdf.as_bytes())],
+            ..Default::default()
+        };
+        for (tipo, numero) in solicitudes {
+            reporte
+                .solicitudes
+                .entry(tipo.to_string())
+                .or_default()
+                .push(Solicitud {
+                    numero_solicitud: numero.to_string(),
+                    nombre_del_estudiante: pdf.to_string(),
+                    origenes: vec![0],
+                    ..Default::default()
+                });
+        }
+        reporte.sin_procesar.push(SinProcesar {
+            posicion: 1,
+            texto: pdf.to_string(),
+            paginas: None,
+            motivo: String::new(),
+            origen: 0,
+        });
+        reporte
+    }
+
+    #[test]
+    fn consolidar_une_solicitudes_repetidas_y_recuerda_sus_reportes() {
+        let consolidado = Reporte::consolidar(vec![
+            reporte("a.pdf", &[(CEA, "CEA1"), (CS, "CS1")]),
+            reporte("b.pdf", &[(CEA, "CEA1"), (CEA, "CEA2")]),
+        ]);
+
+        assert_eq!(consolidado.procedencias.len(), 2);
+        let cea = &consolidado.solicitudes[CEA];
+        assert_eq!(cea.len(), 2);
+        assert_eq!(cea[0].numero_solicitud, "CEA1");
+        // The first report's data wins, and both reports are remembered.
+        assert_eq!(cea[0].nombre_del_estudiante, "a.pdf");
+        assert_eq!(cea[0].origenes, vec![0, 1]);
+        assert_eq!(cea[1].origenes, vec![1]);
+        assert_eq!(consolidado.solicitudes[CS][0].origenes, vec![0]);
+    }
+
+    #[test]
+    fn consolidar_no_une_solicitudes_sin_numero() {
+        let consolidado = Reporte::consolidar(vec![
+            reporte("a.pdf", &[(CEA, "")]),
+            reporte("b.pdf", &[(CEA, "")]),
+        ]);
+
+        assert_eq!(consolidado.solicitudes[CEA].len(), 2);
+        let origenes: Vec<usize> = consolidado
+            .sin_procesar
+            .iter()
+            .map(|chunk| chunk.origen)
+            .collect();
+        assert_eq!(origenes, vec![0, 1]);
+    }
+
+    const RTG_CHUNK: &str = "nombre del estudiante Ana Pérez identificación 1037654321 \
+        plan de estudios Ingeniería de Sistemas número y fecha de la solicitud RTG-123 \
+        12/03/2024 anexar otros documentos físicos documento anexo Documento";
+
+    #[test]
+    fn volcado_de_sin_procesar_se_lee_igual_que_se_escribio() {
+        let dir = std::env::temp_dir().join(format!("reps-sia-{}-volcado", std::process::id()));
+        let _ = std::fs::remove_dir_all(&dir);
+        std::fs::create_dir_all(&dir).unwrap();
+        // Text of a report, told apart from a dump by its "SOLICITUD
+        // ESTUDIANTE" headers: a handled request and two chunks that aren't.
+        let texto = format!(
+            "Agenda\n1. | SOLICITUD ESTUDIANTE\n{RTG_CHUNK}\n\
+             2. | SOLICITUD ESTUDIANTE\ntexto que no es una solicitud\n\
+             3. | SOLICITUD ESTUDIANTE\notro fragmento suelto\n"
+        );
+        std::fs::write(dir.join("agenda.txt"), texto).unwrap();
+        let reporte = analizar(&dir.join("agenda.txt"), None);
+        let volcado = dir.join("volcado.txt");
+        let releido = reporte.as_ref().map_err(Clone::clone).and_then(|reporte| {
+            write_unhandled(volcado.clone(), &reporte.sin_procesar).unwrap();
+            analizar(&volcado, None)
+        });
+        let _ = std::fs::remove_dir_all(&dir);
+
+        let reporte = reporte.unwrap();
+        let releido = releido.unwrap();
+        assert_eq!(reporte.solicitudes[RTG].len(), 1);
+        assert!(releido.solicitudes.is_empty());
+        let fragmentos = |reporte: &Reporte| -> Vec<(String, String)> {
+            reporte
+                .sin_procesar
+                .iter()
+                .map(|chunk| (chunk.texto.trim().to_string(), chunk.motivo.clone()))
+                .collect()
+        };
+        assert_eq!(fragmentos(&reporte).len(), 2);
+        assert_eq!(fragmentos(&releido), fragmentos(&reporte));
+    }
+
+    #[test]
+    fn volcado_recupera_las_solicitudes_que_ya_se_pueden_leer() {
+        let volcado = std::env::temp_dir().join(format!(
+            "reps-sia-{}-volcado-recuperado.txt",
+            std::process::id()
+        ));
+        let chunk = SinProcesar {
+            posicion: 1,
+            texto: RTG_CHUNK.to_string(),
+            paginas: None,
+            motivo: String::new(),
+            origen: 0,
+        };
+        write_unhandled(volcado.clone(), &[chunk]).unwrap();
+        let releido = analizar(&volcado, None);
+        let _ = std::fs::remove_file(&volcado);
+
+        let releido = releido.unwrap();
+        assert!(releido.sin_procesar.is_empty());
+        assert_eq!(releido.solicitudes[RTG][0].numero_solicitud, "RTG-123");
+    }
+
+    /// Targets of the links in the first sheet, as Excel will read them.
+    fn destinos_de_enlaces(uris: &[String]) -> String {
+        let path =
+            std::env::temp_dir().join(format!("reps-sia-{}-enlaces.xlsx", std::process::id()));
+        let mut workbook = Workbook::new();
+        let worksheet = workbook.add_worksheet();
+        for (row, uri) in uris.iter().enumerate() {
+            worksheet
+                .write_url(row as u32, 0, Url::new(uri.as_str()).set_text("1"))
+                .unwrap();
+        }
+        workbook.save(&path).unwrap();
+
+        let archivo = std::fs::File::open(&path).unwrap();
+        let mut zip = zip::ZipArchive::new(archivo).unwrap();
+        let mut rels = String::new();
+        zip.by_name("xl/worksheets/_rels/sheet1.xml.rels")
+            .unwrap()
+            .read_to_string(&mut rels)
+            .unwrap();
+        std::fs::remove_file(&path).unwrap();
+        rels
+    }
+
+    #[test]
+    fn enlaces_al_pdf_apuntan_a_la_ruta_absoluta() {
+        let rels = destinos_de_enlaces(&[
+            uri_de("/home/u/reportes/agenda.pdf"),
+            uri_de(r"C:\Reportes\agenda.pdf"),
+            uri_de(r"\\servidor\compartida\agenda.pdf"),
+        ]);
+
+        assert!(
+            rels.contains(r#"Target="/home/u/reportes/agenda.pdf""#),
+            "{rels}"
+        );
+        assert!(
+            rels.contains(r#"Target="file:///C:/Reportes/agenda.pdf""#),
+            "{rels}"
+        );
+        assert!(
+            rels.contains(r#"Target="file:///\\servidor\compartida\agenda.pdf""#),
+            "{rels}"
+        );
+    }
+
+    #[test]
+    fn la_pagina_se_escribe_aparte_del_enlace() {
+        let mut reporte = reporte("agenda.pdf", &[(RTG, "RTG-1")]);
+        let solicitud = &mut reporte.solicitudes.get_mut(RTG).unwrap()[0];
+        solicitud.adjuntos = Some(1);
+        solicitud.paginas = Some((3, 4));
+        reporte.sin_procesar[0].paginas = Some((5, 5));
+        let path =
+            std::env::temp_dir().join(format!("reps-sia-{}-paginas.xlsx", std::process::id()));
+        write_data_to_excel(&reporte, &path).unwrap();
+
+        let mut libro = calamine::open_workbook_auto(&path).unwrap();
+        let fila = |libro: &mut calamine::Sheets<_>, hoja: &str, encabezado: &str| {
+            let hoja = calamine::Reader::worksheet_range(libro, hoja).unwrap();
+            let mut filas = hoja.rows();
+            let col = filas
+                .next()
+                .unwrap()
+                .iter()
+                .position(|c| *c == encabezado)
+                .unwrap();
+            filas.next().unwrap()[col].to_string()
+        };
+        let solicitud_pagina = fila(&mut libro, RTG, "Página(s)");
+        let solicitud_pdf = fila(&mut libro, RTG, PDF);
+        let sin_procesar_pagina = fila(&mut libro, SIN_PROCESAR, "Página(s)");
+        let sin_procesar_pdf = fila(&mut libro, SIN_PROCESAR, PDF);
+        std::fs::remove_file(&path).unwrap();
+
+        assert_eq!(solicitud_pagina, "3-4");
+        assert_eq!(solicitud_pdf, "Abrir");
+        assert_eq!(sin_procesar_pagina, "5");
+        assert_eq!(sin_procesar_pdf, "Abrir");
+    }
+
+    #[test]
+    fn ruta_libre_con_numera_hasta_encontrar_una_libre() {
+        let ocupadas = [PathBuf::from("a/b.xlsx"), PathBuf::from("a/b (1).xlsx")];
+        let ocupada = |path: &Path| ocupadas.iter().any(|o| o == path);
+
+        assert_eq!(
+            ruta_libre_con(PathBuf::from("a/c.xlsx"), ocupada),
+            PathBuf::from("a/c.xlsx")
+        );
+        assert_eq!(
+            ruta_libre_con(PathBuf::from("a/b.xlsx"), ocupada),
+            PathBuf::from("a/b (2).xlsx")
+        );
+        assert_eq!(
+            ruta_libre_con(PathBuf::from("a/b"), |path| path == Path::new("a/b")),
+            PathBuf::from("a/b (1)")
+        );
+    }
+
+    /// PDF with one page per text; a `None` page has no MediaBox, which
+    /// `pdf_extract` can't read.
+    fn pdf(paginas: &[Option<&str>]) -> Vec<u8> {
+        use pdf_extract::content::{Content, Operation};
+        use pdf_extract::{Object, Stream, dictionary};
+
+        let mut doc = pdf_extract::Document::with_version("1.5");
+        let pages_id = doc.new_object_id();
+        let font_id = doc.add_object(dictionary! {
+            "Type" => "Font",
+            "Subtype" => "Type1",
+            "BaseFont" => "Courier",
+        });
+        let resources_id = doc.add_object(dictionary! {
+            "Font" => dictionary! { "F1" => font_id },
+        });
+        let mut kids = Vec::new();
+        for texto in paginas {
+            let contents_id = match texto {
+                Some(texto) => {
+                    let content = Content {
+                        operations: vec![
+                            Operation::new("BT", vec![]),
+                            Operation::new("Tf", vec!["F1".into(), 12.into()]),
+                            Operation::new("Td", vec![100.into(), 600.into()]),
+                            Operation::new("Tj", vec![Object::string_literal(*texto)]),
+                            Operation::new("ET", vec![]),
+                        ],
+                    };
+                    doc.add_object(Stream::new(dictionary! {}, content.encode().unwrap()))
+                }
+                None => doc.add_object(Stream::new(dictionary! {}, b"BT ET".to_vec())),
+            };
+            let mut page = dictionary! {
+                "Type" => "Page",
+                "Parent" => pages_id,
+                "Contents" => contents_id,
+            };
+            if texto.is_some() {
+                page.set("MediaBox", vec![0.into(), 0.into(), 595.into(), 842.into()]);
+            }
+            let page_id = doc.add_object(page);
+            kids.push(page_id.into());
+        }
+        doc.objects.insert(
+            pages_id,
+            Object::Dictionary(dictionary! {
+                "Type" => "Pages",
+                "Count" => kids.len() as i64,
+                "Kids" => kids,
+                "Resources" => resources_id,
+            }),
+        );
+        let catalog_id = doc.add_object(dictionary! {
+            "Type" => "Catalog",
+            "Pages" => pages_id,
+        });
+        doc.trailer.set("Root", catalog_id);
+        let mut bytes = Vec::new();
+        doc.save_to(&mut bytes).unwrap();
+        bytes
+    }
+
+    #[test]
+    fn extract_pages_devuelve_cada_pagina() {
+        let (texto, inicios) = extract_pages(&pdf(&[Some("uno"), Some("dos")])).unwrap();
+
+        assert_eq!(inicios.len(), 2);
+        assert!(texto[..inicios[1]].contains("uno"), "{texto:?}");
+        assert!(texto[inicios[1]..].contains("dos"), "{texto:?}");
+    }
+
+    #[test]
+    fn extract_pages_falla_si_una_pagina_no_se_puede_leer() {
+        let error = extract_pages(&pdf(&[Some("uno"), None, Some("tres")])).unwrap_err();
+
+        assert!(error.contains("page 2"), "{error}");
     }
 }
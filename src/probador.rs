@@ -0,0 +1,278 @@
+use crate::idioma::{tr, trf};
+use crate::pdf_handling::{self, ANOTACIONES, Campo, Reporte};
+use crate::reglas::{self, Regla};
+use crate::trabajo::{Evento, Tarea, Trabajo};
+use eframe::egui;
+use rfd::FileDialog;
+
+/// Outcome of trying a rule on a report chunk.
+enum Prueba {
+    /// The pattern doesn't compile.
+    Error(String),
+    Resultado {
+        /// Groups captured by the edited rule, `None` if it didn't match.
+        capturas: Option<Vec<Option<String>>>,
+        /// Where the chunk ended up once every rule is tried, and its fields.
+        tipo: Option<String>,
+        fila: Vec<(Campo, String)>,
+        /// Why the chunk was left unhandled, if it was.
+        motivo: Option<String>,
+    },
+}
+
+/// Panel to edit the rules and see right away what they extract from a chunk.
+pub struct Probador {
+    regla: Regla,
+    patron: String,
+    fragmento: String,
+    /// Chunks of a loaded report: label and text.
+    fragmentos: Vec<(String, String)>,
+    prueba: Option<Prueba>,
+    /// Report being loaded in the background.
+    trabajo: Option<Trabajo>,
+}
+
+impl Probador {
+    pub fn new(regla: Regla) -> Self {
+        Self {
+            regla,
+            patron: reglas::activas().patron(regla).to_string(),
+            fragmento: String::new(),
+            fragmentos: Vec::new(),
+            prueba: None,
+            trabajo: None,
+        }
+    }
+
+    pub fn regla(&self) -> Regla {
+        self.regla
+    }
+
+    fn probar(&mut self) {
+        let reglas = match reglas::activas().con_patron(self.regla, &self.patron) {
+            Ok(reglas) => reglas,
+            Err(e) => {
+                self.prueba = Some(Prueba::Error(e));
+                return;
+            }
+        };
+        let capturas = reglas.regex(self.regla).captures(&self.fragmento).map(|c| {
+            c.iter()
+                .skip(1)
+                .map(|m| m.map(|m| m.as_str().to_string()))
+                .collect()
+        });
+        let prueba = match pdf_handling::probar_reglas(&self.fragmento, &reglas) {
+            Ok((solicitudes, sin_procesar)) => {
+                let clasificada = solicitudes
+                    .into_iter()
+                    .filter(|(tipo, _)| tipo != ANOTACIONES)
+                    .find_map(|(tipo, mut lista)| lista.pop().map(|sol| (tipo, sol)));
+                Prueba::Resultado {
+                    capturas,
+                    tipo: clasificada.as_ref().map(|(tipo, _)| tipo.clone()),
+                    fila: clasificada
+                        .map(|(_, sol)| sol.iter().collect())
+                        .unwrap_or_default(),
+                    motivo: sin_procesar.into_iter().next().map(|s| s.motivo),
+                }
+            }
+            Err(e) => Prueba::Error(e),
+        };
+        self.prueba = Some(prueba);
+    }
+
+    /// Starts parsing a report the user picks, so its chunks can be tried.
+    fn cargar_reporte(&mut self, ctx: &egui::Context) {
+        if let Some(path) = FileDialog::new()
+            .add_filter(tr("PDF o texto"), &["pdf", "txt"])
+            .pick_file()
+        {
+            self.trabajo = Some(Trabajo::aparte(Tarea::Analizar(path), ctx.clone()));
+        }
+    }
+
+    /// Handles the report being loaded, returning a message once it is.
+    fn recibir(&mut self) -> Option<String> {
+        let trabajo = self.trabajo.as_mut()?;
+        let eventos = trabajo.recibir();
+        if trabajo.terminado {
+            self.trabajo = None;
+        }
+        let mut mensaje = None;
+        for evento in eventos {
+            if let Evento::Analizado(resultado) = evento {
+                mensaje = Some(match resultado {
+                    Ok(reporte) => trf("{} fragmentos cargados", &[&self.cargar(&reporte)]),
+                    Err(e) => format!("Error: {e}"),
+                });
+            }
+        }
+        mensaje
+    }
+
+    fn cargar(&mut self, reporte: &Reporte) -> usize {
+        let mut fragmentos = Vec::new();
+        for (tipo, solicitudes) in &reporte.solicitudes {
+            if tipo == ANOTACIONES {
+                continue;
+            }
+            for sol in solicitudes {
+                fragmentos.push((sol.numero_solicitud.clone(), sol.texto_fuente.clone()));
+            }
+        }
+        fragmentos.sort();
+        for chunk in &reporte.sin_procesar {
+            fragmentos.push((
+                trf("Sin procesar {}", &[&chunk.posicion]),
+                chunk.texto.trim().to_string(),
+            ));
+        }
+        self.fragmentos = fragmentos;
+        self.fragmentos.len()
+    }
+
+    /// Draws the panel, returning a message for the status line when there is one.
+    pub fn ui(&mut self, ui: &mut egui::Ui) -> Option<String> {
+        let mut mensaje = self.recibir();
+        let mut cambio = self.prueba.is_none();
+
+        ui.horizontal(|ui| {
+            ui.label(tr("Tipo de solicitud:"));
+            let anterior = self.regla;
+            egui::ComboBox::from_id_salt("regla")
+                .selected_text(self.regla.sigla())
+                .show_ui(ui, |ui| {
+                    for regla in Regla::TODAS {
+                        ui.selectable_value(&mut self.regla, regla, regla.sigla());
+                    }
+                });
+            if self.regla != anterior {
+                self.patron = reglas::activas().patron(self.regla).to_string();
+                cambio = true;
+            }
+        });
+
+        ui.label(tr("Regla (expresión regular, cada grupo es un campo):"));
+        cambio |= ui
+            .add(
+                egui::TextEdit::multiline(&mut self.patron)
+                    .code_editor()
+                    .desired_rows(4)
+                    .desired_width(f32::INFINITY),
+            )
+            .changed();
+
+        ui.horizontal(|ui| {
+            if ui.button(tr("Guardar regla")).clicked() {
+                let guardada = reglas::activas()
+                    .con_patron(self.regla, &self.patron)
+                    .and_then(|reglas| reglas.guardar().map(|_| reglas));
+                mensaje = Some(match guardada {
+                    Ok(reglas) => {
+                        reglas::usar(reglas);
+                        trf("Regla {} guardada", &[&self.regla.sigla()])
+                    }
+                    Err(e) => format!("Error: {e}"),
+                });
+            }
+            if ui.button(tr("Restaurar predeterminada")).clicked() {
+                self.patron = self.regla.predeterminada().to_string();
+                cambio = true;
+            }
+        });
+
+        ui.separator();
+        ui.horizontal(|ui| {
+            ui.label(tr("Fragmento del reporte:"));
+            if let Some(trabajo) = &self.trabajo {
+                trabajo.ui(ui);
+            } else if ui.button(tr("Cargar de un reporte")).clicked() {
+                self.cargar_reporte(ui.ctx());
+            }
+            if !self.fragmentos.is_empty() {
+                egui::ComboBox::from_id_salt("fragmento")
+                    .selected_text(tr("Elegir fragmento"))
+                    .show_ui(ui, |ui| {
+                        for (etiqueta, texto) in &self.fragmentos {
+                            if ui.selectable_label(false, etiqueta).clicked() {
+                                self.fragmento.clone_from(texto);
+                                cambio = true;
+                            }
+                        }
+                    });
+            }
+        });
+        cambio |= ui
+            .add(
+                egui::TextEdit::multiline(&mut self.fragmento)
+                    .desired_rows(6)
+                    .desired_width(f32::INFINITY),
+            )
+            .changed();
+
+        if cambio {
+            self.probar();
+        }
+
+        ui.separator();
+        egui::ScrollArea::vertical()
+            .max_height(300.0)
+            .show(ui, |ui| match &self.prueba {
+                Some(Prueba::Error(e)) => {
+                    ui.colored_label(ui.visuals().error_fg_color, e);
+                }
+                Some(Prueba::Resultado {
+                    capturas,
+                    tipo,
+                    fila,
+                    motivo,
+                }) => {
+                    match capturas {
+                        Some(capturas) => {
+                            ui.strong(trf("{} coincide", &[&self.regla.sigla()]));
+                            egui::Grid::new("capturas").striped(true).show(ui, |ui| {
+                                for (i, captura) in capturas.iter().enumerate() {
+                                    ui.label(trf("Grupo {}", &[&(i + 1)]));
+                                    ui.monospace(format!(
+                                        "{:?}",
+                                        captura.as_deref().unwrap_or(tr("<sin captura>"))
+                                    ));
+                                    ui.end_row();
+                                }
+                            });
+                        }
+                        None => {
+                            ui.strong(trf("{} no coincide", &[&self.regla.sigla()]));
+                        }
+                    }
+
+                    ui.add_space(8.0);
+                    match tipo {
+                        Some(tipo) => {
+                            ui.strong(trf("Fila en la hoja \"{}\"", &[&tr(tipo)]));
+                            egui::Grid::new("fila").striped(true).show(ui, |ui| {
+                                for (campo, _) in fila {
+                                    ui.label(campo.encabezado());
+                                }
+                                ui.end_row();
+                                for (_, valor) in fila {
+                                    ui.label(valor);
+                                }
+                                ui.end_row();
+                            });
+                        }
+                        None => {
+                            ui.strong(trf(
+                                "Queda sin procesar: {}",
+                                &[&motivo.as_deref().unwrap_or_default()],
+                            ));
+                        }
+                    }
+                }
+                None => {}
+            });
+
+        mensaje
+    }
+}
@@ -0,0 +1,165 @@
+use crate::ajustes;
+use regex::Regex;
+use serde::{Deserialize, Serialize};
+use std::path::PathBuf;
+use std::sync::{Arc, LazyLock, RwLock};
+
+const RS_RE_CEA: &str = concat!(
+    r"(?s)nombre del estudiante\s*(.+)\s*identificación\s*(\d+\s*\d*)\s*plan de estudios\s*(.+?)\s*número y fecha de la solicitud\s*([A-Z\d\s-]+)\s+(\d\s*\d/\d{2}/\d{4}|\d{2}/\d{2}/\d{2})\s*",
+    r"(?:motivos\s*(.*))(?:anexar otros documentos físicos\s*(.*))",
+    r"(?:materias relacionadas a la solicitud\s*(?:asignatura grp nombre)?(.*))",
+);
+
+const RS_RE_CS: &str = concat!(
+    r"(?s)nombre del estudiante\s*(.+?)\s*identificación\s*(\d+\s*\d*)\s*plan de estudios\s*(.+?)\s*número y fecha de la solicitud\s*([A-Z\d\s-]+)\s+(\d\s*\d/\d{2}/\d{4}|\d{2}/\d{2}/\d{2})\s*",
+    r"(?:motivos\s*(.*))(?:anexar otros documentos físicos\s*(.*))",
+    r"(?:periodo para el que solicita cancelación de semestre\s*(.*))",
+);
+
+const RS_RE_ACM: &str = concat!(
+    r"(?s)nombre del estudiante\s*(.+?)\s*identificación\s*(\d+\s*\d*)\s*plan de estudios\s*(.+?)\s*número y fecha de la solicitud\s*([A-Z\d\s-]+)\s+(\d\s*\d/\d{2}/\d{4}|\d{2}/\d{2}/\d{2})\s*",
+    r"(?:motivos\s*(.*))(?:anexar otros documentos físicos\s*(.*))",
+    r"(?:periodo para el que solicita carga mínima\s*(.*))",
+);
+
+const RS_RE_RTG: &str = r"(?s)nombre del estudiante\s*(.+?)\s*identificación\s*(\d+\s*\d*)\s*plan de estudios\s*(.+?)\s*número y fecha de la solicitud\s*([^ ]+)\s+(\d\s*\d/\d{2}/\d{4}|\d{2}/\d{2}/\d{2})\s*(?:anexar otros documentos físicos\s*(.*))";
+
+/// Rule of each request type, in the order they are tried.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub enum Regla {
+    Cea,
+    Cs,
+    Acm,
+    Rtg,
+}
+
+impl Regla {
+    pub const TODAS: [Regla; 4] = [Regla::Cea, Regla::Cs, Regla::Acm, Regla::Rtg];
+
+    /// Key in the user's rules file.
+    pub fn sigla(self) -> &'static str {
+        match self {
+            Regla::Cea => "CEA",
+            Regla::Cs => "CS",
+            Regla::Acm => "ACM",
+            Regla::Rtg => "RTG",
+        }
+    }
+
+    /// Name shown in traces, the same as the constant it comes from.
+    pub fn nombre(self) -> &'static str {
+        match self {
+            Regla::Cea => "RS_RE_CEA",
+            Regla::Cs => "RS_RE_CS",
+            Regla::Acm => "RS_RE_ACM",
+            Regla::Rtg => "RS_RE_RTG",
+        }
+    }
+
+    pub fn predeterminada(self) -> &'static str {
+        match self {
+            Regla::Cea => RS_RE_CEA,
+            Regla::Cs => RS_RE_CS,
+            Regla::Acm => RS_RE_ACM,
+            Regla::Rtg => RS_RE_RTG,
+        }
+    }
+
+    fn indice(self) -> usize {
+        self as usize
+    }
+}
+
+/// Compiled regex of each request type.
+#[derive(Debug, Clone)]
+pub struct Reglas {
+    regexes: [Regex; 4],
+}
+
+fn compilar(regla: Regla, patron: &str) -> Result<Regex, String> {
+    Regex::new(patron).map_err(|e| format!("Error compiling {}: {e}", regla.sigla()))
+}
+
+/// User overrides of the built-in rules.
+pub fn ruta_predeterminada() -> Option<PathBuf> {
+    ajustes::dirs().map(|dirs| dirs.config_dir().join("reglas.toml"))
+}
+
+impl Reglas {
+    pub fn predeterminadas() -> Self {
+        Self {
+            regexes: Regla::TODAS.map(|regla| {
+                compilar(regla, regla.predeterminada()).expect("built-in rules compile")
+            }),
+        }
+    }
+
+    /// Built-in rules with the overrides in the user's rules file, if any.
+    pub fn cargar() -> Result<Self, String> {
+        let mut reglas = Self::predeterminadas();
+        let Some(path) = ruta_predeterminada().filter(|path| path.exists()) else {
+            return Ok(reglas);
+        };
+        let contenido = std::fs::read_to_string(&path)
+            .map_err(|e| format!("Error reading '{}': {e}", path.display()))?;
+        let tabla: toml::Table = contenido
+            .parse()
+            .map_err(|e| format!("Error parsing '{}': {e}", path.display()))?;
+        for regla in Regla::TODAS {
+            if let Some(patron) = tabla.get(regla.sigla()).and_then(|v| v.as_str()) {
+                reglas = reglas.con_patron(regla, patron)?;
+            }
+        }
+        Ok(reglas)
+    }
+
+    /// Copy with `regla` replaced by `patron`.
+    pub fn con_patron(&self, regla: Regla, patron: &str) -> Result<Self, String> {
+        let mut reglas = self.clone();
+        reglas.regexes[regla.indice()] = compilar(regla, patron)?;
+        Ok(reglas)
+    }
+
+    pub fn regex(&self, regla: Regla) -> &Regex {
+        &self.regexes[regla.indice()]
+    }
+
+    pub fn patron(&self, regla: Regla) -> &str {
+        self.regex(regla).as_str()
+    }
+
+    /// Saves the rules that differ from the built-in ones to the user's rules file.
+    pub fn guardar(&self) -> Result<(), String> {
+        let path = ruta_predeterminada().ok_or("No user config directory available")?;
+        if let Some(parent) = path.parent() {
+            std::fs::create_dir_all(parent)
+                .map_err(|e| format!("Error creating '{}': {e}", parent.display()))?;
+        }
+        let mut tabla = toml::Table::new();
+        for regla in Regla::TODAS {
+            if self.patron(regla) != regla.predeterminada() {
+                tabla.insert(regla.sigla().to_string(), self.patron(regla).into());
+            }
+        }
+        std::fs::write(&path, tabla.to_string())
+            .map_err(|e| format!("Error writing '{}': {e}", path.display()))
+    }
+}
+
+static ACTIVAS: LazyLock<RwLock<Arc<Reglas>>> = LazyLock::new(|| {
+    let reglas = Reglas::cargar().unwrap_or_else(|e| {
+        log::warn!("Using built-in rules: {e}");
+        Reglas::predeterminadas()
+    });
+    RwLock::new(Arc::new(reglas))
+});
+
+/// Rules used to parse reports.
+pub fn activas() -> Arc<Reglas> {
+    ACTIVAS.read().expect("rules lock").clone()
+}
+
+/// Makes `reglas` the ones used from now on.
+pub fn usar(reglas: Reglas) {
+    *ACTIVAS.write().expect("rules lock") = Arc::new(reglas);
+}
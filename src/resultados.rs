@@ -0,0 +1,251 @@
+use crate::idioma::{tr, trf};
+use crate::pdf_handling::{ACM, CEA, CS, RTG, Reporte};
+use eframe::egui;
+use serde::{Deserialize, Serialize};
+use std::path::{Path, PathBuf};
+use std::process::Command;
+
+/// Request types shown as columns, with their short name.
+const TIPOS: [(&str, &str); 4] = [(CEA, "CEA"), (CS, "CS"), (ACM, "ACM"), (RTG, "RTG")];
+
+/// What to open when processing finishes.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, Serialize, Deserialize)]
+pub enum AlTerminar {
+    #[default]
+    Nada,
+    Excel,
+    Carpeta,
+}
+
+impl AlTerminar {
+    pub const TODOS: [AlTerminar; 3] = [AlTerminar::Nada, AlTerminar::Excel, AlTerminar::Carpeta];
+
+    pub fn nombre(self) -> &'static str {
+        tr(match self {
+            AlTerminar::Nada => "Nada",
+            AlTerminar::Excel => "Abrir el excel",
+            AlTerminar::Carpeta => "Mostrar en la carpeta",
+        })
+    }
+}
+
+#[derive(Debug)]
+struct Conteo {
+    /// Requests per type, in the order of `TIPOS`.
+    por_tipo: [usize; 4],
+    sin_procesar: usize,
+    conflictos: usize,
+}
+
+/// Outcome of one processed file, a row of the table.
+#[derive(Debug)]
+struct Fila {
+    archivo: String,
+    salida: Option<PathBuf>,
+    estado: Result<Conteo, String>,
+}
+
+/// Table with the outcome of each file of the last run.
+#[derive(Debug, Default)]
+pub struct Resultados {
+    filas: Vec<Fila>,
+}
+
+/// Opens the file manager on the folder of `path`, selecting it where the
+/// platform allows.
+fn mostrar_en_carpeta(path: &Path) -> std::io::Result<()> {
+    if cfg!(target_os = "windows") {
+        let mut seleccionar = std::ffi::OsString::from("/select,");
+        seleccionar.push(path);
+        Command::new("explorer")
+            .arg(seleccionar)
+            .spawn()
+            .map(|_| ())
+    } else if cfg!(target_os = "macos") {
+        Command::new("open").arg("-R").arg(path).spawn().map(|_| ())
+    } else {
+        open::that_detached(path.parent().unwrap_or(Path::new(".")))
+    }
+}
+
+fn error_al_abrir(path: &Path, e: std::io::Error) -> String {
+    trf("Error: no se pudo abrir '{}': {}", &[&path.display(), &e])
+}
+
+fn nombre(path: &Path) -> String {
+    path.file_name()
+        .unwrap_or_default()
+        .to_string_lossy()
+        .to_string()
+}
+
+impl Resultados {
+    pub fn limpiar(&mut self) {
+        self.filas.clear();
+    }
+
+    pub fn exitosos(&self) -> usize {
+        self.filas.iter().filter(|fila| fila.estado.is_ok()).count()
+    }
+
+    pub fn len(&self) -> usize {
+        self.filas.len()
+    }
+
+    /// Adds a row per source report, so consolidated runs still show each PDF.
+    pub fn agregar(&mut self, reporte: &Reporte, salida: &Path) {
+        for (origen, procedencia) in reporte.procedencias.iter().enumerate() {
+            let por_tipo = TIPOS.map(|(tipo, _)| {
+                reporte.solicitudes.get(tipo).map_or(0, |solicitudes| {
+                    solicitudes
+                        .iter()
+                        .filter(|sol| sol.origenes.contains(&origen))
+                        .count()
+                })
+            });
+            let sin_procesar = reporte
+                .sin_procesar
+                .iter()
+                .filter(|chunk| chunk.origen == origen)
+                .count();
+            // Conflicts are found after consolidating, they belong to the output.
+            let conflictos = if origen == 0 {
+                reporte.conflictos.len()
+            } else {
+                0
+            };
+            self.filas.push(Fila {
+                archivo: procedencia.archivo(),
+                salida: Some(salida.to_path_buf()),
+                estado: Ok(Conteo {
+                    por_tipo,
+                    sin_procesar,
+                    conflictos,
+                }),
+            });
+        }
+    }
+
+    /// Excel files written, once each even if consolidated from several PDFs.
+    fn salidas(&self) -> Vec<&Path> {
+        let mut salidas: Vec<&Path> = Vec::new();
+        for fila in &self.filas {
+            if fila.estado.is_ok()
+                && let Some(salida) = &fila.salida
+                && !salidas.contains(&salida.as_path())
+            {
+                salidas.push(salida);
+            }
+        }
+        salidas
+    }
+
+    /// Does what `al_terminar` says with the excel files written, returning
+    /// an error message if one couldn't be opened. Folders are shown once.
+    pub fn abrir(&self, al_terminar: AlTerminar) -> Option<String> {
+        let mut carpetas = Vec::new();
+        let mut error = None;
+        for salida in self.salidas() {
+            let resultado = match al_terminar {
+                AlTerminar::Nada => return None,
+                AlTerminar::Excel => open::that_detached(salida),
+                AlTerminar::Carpeta => {
+                    let carpeta = salida.parent();
+                    if carpetas.contains(&carpeta) {
+                        continue;
+                    }
+                    carpetas.push(carpeta);
+                    mostrar_en_carpeta(salida)
+                }
+            };
+            if let Err(e) = resultado {
+                error = Some(error_al_abrir(salida, e));
+            }
+        }
+        error
+    }
+
+    pub fn agregar_error(&mut self, entradas: &[PathBuf], error: String) {
+        let archivo = entradas.iter().map(|p| nombre(p)).collect::<Vec<_>>();
+        self.filas.push(Fila {
+            archivo: archivo.join(", "),
+            salida: None,
+            estado: Err(error),
+        });
+    }
+
+    /// Draws the table, returning an error message if an output couldn't be opened.
+    pub fn ui(&self, ui: &mut egui::Ui) -> Option<String> {
+        if self.filas.is_empty() {
+            return None;
+        }
+        let mut mensaje = None;
+        egui::ScrollArea::both()
+            .id_salt("resultados")
+            .max_height(250.0)
+            .show(ui, |ui| {
+                egui::Grid::new("resultados")
+                    .striped(true)
+                    .num_columns(TIPOS.len() + 6)
+                    .show(ui, |ui| {
+                        ui.strong(tr("Archivo"));
+                        ui.strong(tr("Estado"));
+                        for (tipo, sigla) in TIPOS {
+                            ui.strong(sigla).on_hover_text(tr(tipo));
+                        }
+                        ui.strong(tr("Sin procesar"));
+                        ui.strong(tr("Conflictos"));
+                        ui.strong("Excel");
+                        ui.label("");
+                        ui.end_row();
+
+                        for fila in &self.filas {
+                            ui.label(&fila.archivo);
+                            match &fila.estado {
+                                Ok(conteo) => {
+                                    ui.label(tr("Listo"));
+                                    for n in conteo.por_tipo {
+                                        ui.label(n.to_string());
+                                    }
+                                    ui.label(conteo.sin_procesar.to_string());
+                                    ui.label(conteo.conflictos.to_string());
+                                }
+                                Err(e) => {
+                                    ui.colored_label(ui.visuals().error_fg_color, tr("Error"))
+                                        .on_hover_text(e);
+                                    for _ in 0..TIPOS.len() + 2 {
+                                        ui.label("");
+                                    }
+                                }
+                            }
+                            match &fila.salida {
+                                Some(salida) => {
+                                    let boton = ui
+                                        .button(tr("Abrir"))
+                                        .on_hover_text(salida.display().to_string());
+                                    if boton.clicked()
+                                        && let Err(e) = open::that_detached(salida)
+                                    {
+                                        mensaje = Some(error_al_abrir(salida, e));
+                                    }
+                                    let boton = ui
+                                        .button(tr("Mostrar en la carpeta"))
+                                        .on_hover_text(salida.display().to_string());
+                                    if boton.clicked()
+                                        && let Err(e) = mostrar_en_carpeta(salida)
+                                    {
+                                        mensaje = Some(error_al_abrir(salida, e));
+                                    }
+                                }
+                                None => {
+                                    ui.label("");
+                                    ui.label("");
+                                }
+                            }
+                            ui.end_row();
+                        }
+                    });
+            });
+        mensaje
+    }
+}
@@ -0,0 +1,368 @@
+use crate::idioma::{self, tr};
+use crate::pdf_handling::{
+    ANOTACIONES, Campo, DECISION, NOTAS, Reporte, SIN_PROCESAR, SolicitudMap,
+};
+use calamine::{Data, Reader, open_workbook_auto};
+use rust_xlsxwriter::{Format, Workbook, XlsxError};
+use std::collections::HashMap;
+use std::path::Path;
+
+/// Hidden sheet with the PDF values, to tell reviewer edits apart later.
+const ORIGINALES: &str = "Originales";
+const CONFLICTOS: &str = "Conflictos";
+
+/// A field both the reviewer and the new PDF changed; the PDF value wins.
+#[derive(Debug, Clone)]
+pub struct Conflicto {
+    pub numero_solicitud: String,
+    pub campo: Campo,
+    pub valor_revisor: String,
+    pub valor_pdf_anterior: String,
+    pub valor_pdf_nuevo: String,
+}
+
+#[derive(Debug, Default)]
+struct FilaRevisada {
+    valores: HashMap<Campo, String>,
+    decision: String,
+    notas: String,
+}
+
+/// What reviewers left in a workbook reps-sia wrote before.
+#[derive(Debug, Default)]
+pub struct LibroRevisado {
+    filas: HashMap<String, FilaRevisada>,
+    /// Request number -> field -> PDF value back then.
+    originales: HashMap<String, HashMap<Campo, String>>,
+}
+
+fn celda_texto(celda: &Data) -> String {
+    match celda {
+        Data::Empty => String::new(),
+        Data::DateTime(fecha) => fecha
+            .as_datetime()
+            .map(|fecha| fecha.format("%d/%m/%Y").to_string())
+            .unwrap_or_default(),
+        Data::Float(numero) if numero.fract() == 0.0 => (*numero as i64).to_string(),
+        otro => otro.to_string(),
+    }
+}
+
+/// Whether `nombre` is `texto` in any language; books may have been
+/// written with the interface in another one.
+fn es(texto: &'static str, nombre: &str) -> bool {
+    idioma::todas(texto).contains(&nombre)
+}
+
+impl LibroRevisado {
+    pub fn leer(path: &Path) -> Result<Self, String> {
+        let mut workbook = open_workbook_auto(path)
+            .map_err(|e| format!("Error opening '{}': {e}", path.display()))?;
+        let mut libro = LibroRevisado::default();
+
+        for sheet_name in workbook.sheet_names() {
+            // These also have a request number column, but their rows aren't
+            // the reviewed requests and would replace them.
+            if [ANOTACIONES, CONFLICTOS, SIN_PROCESAR]
+                .into_iter()
+                .any(|hoja| es(hoja, &sheet_name))
+            {
+                continue;
+            }
+            let range = workbook
+                .worksheet_range(&sheet_name)
+                .map_err(|e| format!("Error reading sheet '{sheet_name}': {e}"))?;
+            let mut rows = range.rows();
+            let Some(headers) = rows.next() else {
+                continue;
+            };
+            let headers: Vec<String> = headers.iter().map(celda_texto).collect();
+
+            if es(ORIGINALES, &sheet_name) {
+                for row in rows {
+                    let celdas: Vec<String> = row.iter().map(celda_texto).collect();
+                    if let [numero, campo, valor, ..] = celdas.as_slice()
+                        && let Some(campo) = Campo::desde_encabezado(campo)
+                    {
+                        libro
+                            .originales
+                            .entry(numero.clone())
+                            .or_default()
+                            .insert(campo, valor.clone());
+                    }
+                }
+                continue;
+            }
+
+            // Only request sheets have a request number column.
+            let Some(col_numero) = headers
+                .iter()
+                .position(|h| Campo::desde_encabezado(h) == Some(Campo::NumeroSolicitud))
+            else {
+                continue;
+            };
+            for row in rows {
+                let celdas: Vec<String> = row.iter().map(celda_texto).collect();
+                let Some(numero) = celdas.get(col_numero).filter(|n| !n.is_empty()) else {
+                    continue;
+                };
+                let mut fila = FilaRevisada::default();
+                for (header, valor) in headers.iter().zip(&celdas) {
+                    if es(DECISION, header) {
+                        fila.decision = valor.clone();
+                    } else if es(NOTAS, header) {
+                        fila.notas = valor.clone();
+                    } else if let Some(campo) = Campo::desde_encabezado(header)
+                        && !matches!(campo, Campo::NumeroSolicitud | Campo::Paginas)
+                    {
+                        fila.valores.insert(campo, valor.clone());
+                    }
+                }
+                libro.filas.insert(numero.clone(), fila);
+            }
+        }
+
+        Ok(libro)
+    }
+
+    /// Carries decisions, notes and corrections into `reporte`, matching rows by
+    /// request number. A field the reviewer changed is only carried over if the
+    /// PDF still says what it said back then; otherwise a `Conflicto` is recorded
+    /// and the new PDF value is kept. Returns how many fields were corrected.
+    pub fn aplicar(&self, reporte: &mut Reporte) -> usize {
+        let mut corregidos = 0;
+        for (tipo, solicitudes) in reporte.solicitudes.iter_mut() {
+            if tipo == ANOTACIONES {
+                continue;
+            }
+            for sol in solicitudes.iter_mut() {
+                let Some(fila) = self.filas.get(&sol.numero_solicitud) else {
+                    continue;
+                };
+                sol.decision.clone_from(&fila.decision);
+                sol.notas.clone_from(&fila.notas);
+
+                let originales = self.originales.get(&sol.numero_solicitud);
+                for (&campo, valor_revisor) in &fila.valores {
+                    // Workbooks without the hidden sheet can't tell corrections apart.
+                    let Some(anterior) = originales.and_then(|o| o.get(&campo)) else {
+                        continue;
+                    };
+                    if valor_revisor.trim() == anterior.trim() {
+                        continue;
+                    }
+                    let nuevo = sol.valor(campo).unwrap_or_default();
+                    if nuevo.trim() == valor_revisor.trim() {
+                        continue;
+                    }
+                    if nuevo.trim() == anterior.trim() && sol.corregir(campo, valor_revisor).is_ok()
+                    {
+                        corregidos += 1;
+                        continue;
+                    }
+                    reporte.conflictos.push(Conflicto {
+                        numero_solicitud: sol.numero_solicitud.clone(),
+                        campo,
+                        valor_revisor: valor_revisor.clone(),
+                        valor_pdf_anterior: anterior.clone(),
+                        valor_pdf_nuevo: nuevo,
+                    });
+                }
+            }
+        }
+        corregidos
+    }
+}
+
+/// Hidden sheet with the value the PDF gave for every field, before corrections.
+pub fn write_originales(workbook: &mut Workbook, data: &SolicitudMap) -> Result<(), XlsxError> {
+    let worksheet = workbook.add_worksheet().set_name(tr(ORIGINALES))?;
+    let bold_format = Format::new().set_bold();
+    worksheet.write_string_with_format(0, 0, Campo::NumeroSolicitud.encabezado(), &bold_format)?;
+    worksheet.write_string_with_format(0, 1, tr("Campo"), &bold_format)?;
+    worksheet.write_string_with_format(0, 2, tr("Valor"), &bold_format)?;
+
+    let mut row = 1;
+    for (tipo, solicitudes) in data {
+        if tipo == ANOTACIONES {
+            continue;
+        }
+        for sol in solicitudes {
+            for campo in Campo::TODOS {
+                if matches!(campo, Campo::NumeroSolicitud | Campo::Paginas) {
+                    continue;
+                }
+                if let Some(valor) = sol.valor_original(campo) {
+                    worksheet.write_string(row, 0, &sol.numero_solicitud)?;
+                    worksheet.write_string(row, 1, campo.encabezado())?;
+                    worksheet.write_string(row, 2, valor)?;
+                    row += 1;
+                }
+            }
+        }
+    }
+    worksheet.set_hidden(true);
+
+    Ok(())
+}
+
+pub fn write_conflictos(
+    workbook: &mut Workbook,
+    conflictos: &[Conflicto],
+) -> Result<(), XlsxError> {
+    let worksheet = workbook.add_worksheet().set_name(tr(CONFLICTOS))?;
+    let bold_format = Format::new().set_bold();
+    let texto_format = Format::new().set_text_wrap();
+
+    let headers = [
+        Campo::NumeroSolicitud.encabezado(),
+        tr("Campo"),
+        tr("Valor del revisor"),
+        tr("Valor anterior del PDF"),
+        tr("Valor nuevo del PDF"),
+    ];
+    for (col, header) in headers.iter().enumerate() {
+        worksheet.write_string_with_format(0, col as u16, *header, &bold_format)?;
+    }
+
+    for (row, conflicto) in conflictos.iter().enumerate() {
+        let row = row as u32 + 1;
+        worksheet.write_string(row, 0, &conflicto.numero_solicitud)?;
+        worksheet.write_string(row, 1, conflicto.campo.encabezado())?;
+        worksheet.write_string_with_format(row, 2, &conflicto.valor_revisor, &texto_format)?;
+        worksheet.write_string_with_format(row, 3, &conflicto.valor_pdf_anterior, &texto_format)?;
+        worksheet.write_string_with_format(row, 4, &conflicto.valor_pdf_nuevo, &texto_format)?;
+    }
+    for col in 2..=4 {
+        worksheet.set_column_width(col, 50)?;
+    }
+
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::pdf_handling::{CEA, Solicitud};
+    use std::path::PathBuf;
+
+    fn ruta_temporal(nombre: &str) -> PathBuf {
+        std::env::temp_dir().join(format!("reps-sia-{}-{nombre}", std::process::id()))
+    }
+
+    fn solicitud(numero: &str, nombre: &str) -> Solicitud {
+        Solicitud {
+            numero_solicitud: numero.to_string(),
+            nombre_del_estudiante: nombre.to_string(),
+            ..Default::default()
+        }
+    }
+
+    #[test]
+    fn leer_ignora_las_hojas_de_conflictos_y_sin_procesar() {
+        let path = ruta_temporal("revisado.xlsx");
+        let mut workbook = Workbook::new();
+        let hoja = workbook.add_worksheet().set_name(CEA).unwrap();
+        let encabezados = [
+            Campo::NombreDelEstudiante.encabezado(),
+            Campo::NumeroSolicitud.encabezado(),
+            DECISION,
+            NOTAS,
+        ];
+        for (col, encabezado) in encabezados.iter().enumerate() {
+            hoja.write_string(0, col as u16, *encabezado).unwrap();
+        }
+        for (col, valor) in ["Juan Pérez", "CEA-1", "Aprobada", "Con soporte"]
+            .iter()
+            .enumerate()
+        {
+            hoja.write_string(1, col as u16, *valor).unwrap();
+        }
+        let hoja = workbook.add_worksheet().set_name(SIN_PROCESAR).unwrap();
+        hoja.write_string(0, 0, Campo::NumeroSolicitud.encabezado())
+            .unwrap();
+        hoja.write_string(1, 0, "CEA-1").unwrap();
+        write_conflictos(
+            &mut workbook,
+            &[Conflicto {
+                numero_solicitud: "CEA-1".to_string(),
+                campo: Campo::NombreDelEstudiante,
+                valor_revisor: "Juan Pérez".to_string(),
+                valor_pdf_anterior: "Juan Perez".to_string(),
+                valor_pdf_nuevo: "Juan P.".to_string(),
+            }],
+        )
+        .unwrap();
+        workbook.save(&path).unwrap();
+
+        let libro = LibroRevisado::leer(&path);
+        std::fs::remove_file(&path).unwrap();
+        let libro = libro.unwrap();
+
+        let fila = &libro.filas["CEA-1"];
+        assert_eq!(fila.decision, "Aprobada");
+        assert_eq!(fila.notas, "Con soporte");
+        assert_eq!(fila.valores[&Campo::NombreDelEstudiante], "Juan Pérez");
+    }
+
+    fn libro(valor_revisor: &str, original: &str) -> LibroRevisado {
+        let mut libro = LibroRevisado::default();
+        libro.filas.insert(
+            "CEA-1".to_string(),
+            FilaRevisada {
+                valores: HashMap::from([(Campo::NombreDelEstudiante, valor_revisor.to_string())]),
+                decision: "Aprobada".to_string(),
+                notas: String::new(),
+            },
+        );
+        libro.originales.insert(
+            "CEA-1".to_string(),
+            HashMap::from([(Campo::NombreDelEstudiante, original.to_string())]),
+        );
+        libro
+    }
+
+    fn reporte(nombre: &str) -> Reporte {
+        Reporte {
+            solicitudes: HashMap::from([(CEA.to_string(), vec![solicitud("CEA-1", nombre)])]),
+            ..Default::default()
+        }
+    }
+
+    #[test]
+    fn aplicar_traslada_la_correccion_si_el_pdf_no_cambio() {
+        let mut reporte = reporte("Juan Perez");
+        let corregidos = libro("Juan Pérez", "Juan Perez").aplicar(&mut reporte);
+
+        let sol = &reporte.solicitudes[CEA][0];
+        assert_eq!(corregidos, 1);
+        assert_eq!(sol.nombre_del_estudiante, "Juan Pérez");
+        assert_eq!(sol.decision, "Aprobada");
+        assert!(reporte.conflictos.is_empty());
+    }
+
+    #[test]
+    fn aplicar_registra_conflicto_si_el_pdf_cambio() {
+        let mut reporte = reporte("Juan P.");
+        let corregidos = libro("Juan Pérez", "Juan Perez").aplicar(&mut reporte);
+
+        assert_eq!(corregidos, 0);
+        assert_eq!(reporte.solicitudes[CEA][0].nombre_del_estudiante, "Juan P.");
+        let [conflicto] = reporte.conflictos.as_slice() else {
+            panic!("expected one conflict: {:?}", reporte.conflictos);
+        };
+        assert_eq!(conflicto.valor_revisor, "Juan Pérez");
+        assert_eq!(conflicto.valor_pdf_anterior, "Juan Perez");
+        assert_eq!(conflicto.valor_pdf_nuevo, "Juan P.");
+    }
+
+    #[test]
+    fn aplicar_ignora_campos_que_el_revisor_no_cambio() {
+        let mut reporte = reporte("Juan P.");
+        let corregidos = libro("Juan Perez", "Juan Perez").aplicar(&mut reporte);
+
+        assert_eq!(corregidos, 0);
+        assert_eq!(reporte.solicitudes[CEA][0].nombre_del_estudiante, "Juan P.");
+        assert!(reporte.conflictos.is_empty());
+    }
+}
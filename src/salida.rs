@@ -0,0 +1,218 @@
+use crate::pdf_handling::{ANOTACIONES, Reporte, ruta_libre_con};
+use chrono::{Local, NaiveDate};
+use serde::{Deserialize, Serialize};
+use std::path::{Path, PathBuf};
+
+pub const PLANTILLA_PREDETERMINADA: &str = "{stem}.xlsx";
+
+/// Fields a template can use, with what they stand for.
+pub const CAMPOS_PLANTILLA: [(&str, &str); 2] = [
+    (
+        "{stem}",
+        "nombre del reporte sin extensión, \"Consolidado\" al consolidar",
+    ),
+    (
+        "{fecha_reporte}",
+        "fecha de la solicitud más reciente del reporte (AAAA-MM-DD)",
+    ),
+];
+
+/// What to do when the xlsx to write already exists.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, Serialize, Deserialize)]
+pub enum Colision {
+    /// Adds " (n)" to the name, like the txt and trace files.
+    #[default]
+    Sufijo,
+    Sobrescribir,
+}
+
+/// Where the xlsx files of a batch go and how they are named.
+#[derive(Debug, Clone)]
+pub struct Destino {
+    /// `None` writes each excel next to its report.
+    pub carpeta: Option<PathBuf>,
+    pub plantilla: String,
+    pub colision: Colision,
+}
+
+impl Default for Destino {
+    fn default() -> Self {
+        Self {
+            carpeta: None,
+            plantilla: PLANTILLA_PREDETERMINADA.to_string(),
+            colision: Colision::default(),
+        }
+    }
+}
+
+/// Date the report is about: its newest request, or today if it has none.
+fn fecha_reporte(reporte: &Reporte) -> NaiveDate {
+    reporte
+        .solicitudes
+        .iter()
+        .filter(|(tipo, _)| tipo.as_str() != ANOTACIONES)
+        .flat_map(|(_, solicitudes)| solicitudes.iter().map(|sol| sol.fecha_de_solicitud))
+        .max()
+        .unwrap_or_else(|| Local::now().date_naive())
+}
+
+fn stem(reporte: &Reporte) -> String {
+    match reporte.procedencias.as_slice() {
+        [procedencia] => procedencia
+            .pdf_path
+            .file_stem()
+            .unwrap_or_default()
+            .to_string_lossy()
+            .to_string(),
+        _ => "Consolidado".to_string(),
+    }
+}
+
+/// Fills `plantilla`, failing on fields it doesn't know.
+fn nombre(plantilla: &str, stem: &str, fecha: NaiveDate) -> Result<String, String> {
+    let nombre = plantilla
+        .replace("{stem}", stem)
+        .replace("{fecha_reporte}", &fecha.format("%Y-%m-%d").to_string());
+    if nombre.contains(['{', '}']) {
+        return Err(format!("Unknown field in file name template '{plantilla}'"));
+    }
+    if nombre.trim().is_empty() {
+        return Err("Empty file name template".to_string());
+    }
+    if Path::new(&nombre)
+        .extension()
+        .is_some_and(|ext| ext.eq_ignore_ascii_case("xlsx"))
+    {
+        Ok(nombre)
+    } else {
+        Ok(format!("{nombre}.xlsx"))
+    }
+}
+
+impl Destino {
+    /// Checks the template before processing anything.
+    pub fn validar(&self) -> Result<(), String> {
+        nombre(&self.plantilla, "reporte", Local::now().date_naive()).map(|_| ())
+    }
+
+    /// Where `reporte` goes. `usadas` are the paths already given out in this
+    /// run, which are never reused even when overwriting, so two reports
+    /// with the same name don't end up in one excel.
+    pub fn ruta(&self, reporte: &Reporte, usadas: &mut Vec<PathBuf>) -> Result<PathBuf, String> {
+        let carpeta = match &self.carpeta {
+            Some(carpeta) => carpeta.clone(),
+            None => reporte
+                .procedencias
+                .first()
+                .and_then(|procedencia| procedencia.pdf_path.parent())
+                .map(Path::to_path_buf)
+                .unwrap_or_default(),
+        };
+        if !carpeta.as_os_str().is_empty() {
+            std::fs::create_dir_all(&carpeta)
+                .map_err(|e| format!("Error creating '{}': {e}", carpeta.display()))?;
+        }
+        let ruta = carpeta.join(nombre(
+            &self.plantilla,
+            &stem(reporte),
+            fecha_reporte(reporte),
+        )?);
+        let ruta = match self.colision {
+            Colision::Sufijo => ruta_libre_con(ruta, |candidata| {
+                candidata.exists() || usadas.iter().any(|usada| usada == candidata)
+            }),
+            Colision::Sobrescribir => ruta_libre_con(ruta, |candidata| {
+                usadas.iter().any(|usada| usada == candidata)
+            }),
+        };
+        usadas.push(ruta.clone());
+        Ok(ruta)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::pdf_handling::{CEA, Procedencia, Solicitud};
+
+    fn fecha(dia: u32) -> NaiveDate {
+        NaiveDate::from_ymd_opt(2024, 3, dia).unwrap()
+    }
+
+    fn reporte(pdf_path: &Path) -> Reporte {
+        let mut reporte = Reporte {
+            procedencias: vec![Procedencia::new(pdf_path, b"")],
+            ..Default::default()
+        };
+        let solicitudes = [5, 12, 7]
+            .into_iter()
+            .map(|dia| Solicitud {
+                fecha_de_solicitud: fecha(dia),
+                ..Default::default()
+            })
+            .collect();
+        reporte.solicitudes.insert(CEA.to_string(), solicitudes);
+        reporte
+    }
+
+    #[test]
+    fn nombre_llena_los_campos_de_la_plantilla() {
+        assert_eq!(
+            nombre("{stem} {fecha_reporte}.xlsx", "agenda", fecha(12)).unwrap(),
+            "agenda 2024-03-12.xlsx"
+        );
+    }
+
+    #[test]
+    fn nombre_agrega_la_extension_si_falta() {
+        assert_eq!(nombre("{stem}", "agenda", fecha(1)).unwrap(), "agenda.xlsx");
+        assert_eq!(
+            nombre("{stem}.XLSX", "agenda", fecha(1)).unwrap(),
+            "agenda.XLSX"
+        );
+    }
+
+    #[test]
+    fn nombre_rechaza_campos_desconocidos_y_plantillas_vacias() {
+        assert!(nombre("{stem}-{fecha}", "agenda", fecha(1)).is_err());
+        assert!(nombre("  ", "agenda", fecha(1)).is_err());
+    }
+
+    #[test]
+    fn ruta_usa_la_fecha_mas_reciente_y_la_carpeta_del_reporte() {
+        let destino = Destino {
+            plantilla: "{stem}_{fecha_reporte}".to_string(),
+            ..Default::default()
+        };
+        let ruta = destino
+            .ruta(&reporte(Path::new("agenda.pdf")), &mut Vec::new())
+            .unwrap();
+
+        assert_eq!(ruta, PathBuf::from("agenda_2024-03-12.xlsx"));
+    }
+
+    #[test]
+    fn ruta_agrega_sufijo_o_sobrescribe_segun_la_colision() {
+        let carpeta = std::env::temp_dir().join(format!("reps-sia-{}-salida", std::process::id()));
+        let _ = std::fs::remove_dir_all(&carpeta);
+        std::fs::create_dir_all(&carpeta).unwrap();
+        std::fs::write(carpeta.join("agenda.xlsx"), b"").unwrap();
+        let reporte = reporte(Path::new("agenda.pdf"));
+        let destino = |colision| Destino {
+            carpeta: Some(carpeta.clone()),
+            colision,
+            ..Default::default()
+        };
+
+        let sufijo = destino(Colision::Sufijo).ruta(&reporte, &mut Vec::new());
+        let mut usadas = Vec::new();
+        let primera = destino(Colision::Sobrescribir).ruta(&reporte, &mut usadas);
+        let segunda = destino(Colision::Sobrescribir).ruta(&reporte, &mut usadas);
+        let _ = std::fs::remove_dir_all(&carpeta);
+
+        assert_eq!(sufijo.unwrap(), carpeta.join("agenda (1).xlsx"));
+        assert_eq!(primera.unwrap(), carpeta.join("agenda.xlsx"));
+        // Overwriting never hands out a path already used in the run.
+        assert_eq!(segunda.unwrap(), carpeta.join("agenda (1).xlsx"));
+    }
+}
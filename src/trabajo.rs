@@ -0,0 +1,297 @@
+use crate::comparacion::{self, Conteo};
+use crate::idioma::{tr, trf};
+use crate::pdf_handling::{self, Opciones, Reporte};
+use crate::salida::Destino;
+use eframe::egui;
+use std::path::PathBuf;
+use std::sync::Arc;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::mpsc::{self, Receiver, Sender};
+use std::thread;
+
+/// What to produce: an xlsx per report or one with all of them.
+#[derive(Debug, Clone)]
+pub enum Tarea {
+    Uno(PathBuf),
+    Consolidado(Vec<PathBuf>),
+    /// Only parses the report, for the rule tester.
+    Analizar(PathBuf),
+    /// Compares an older report with a newer one, writing the changes to `salida`.
+    Comparar {
+        anterior: PathBuf,
+        nuevo: PathBuf,
+        salida: PathBuf,
+    },
+}
+
+impl Tarea {
+    fn archivos(&self) -> usize {
+        match self {
+            Tarea::Uno(_) | Tarea::Analizar(_) => 1,
+            Tarea::Consolidado(entradas) => entradas.len(),
+            Tarea::Comparar { .. } => 2,
+        }
+    }
+}
+
+/// Messages from the worker thread to the UI.
+#[derive(Debug)]
+pub enum Evento {
+    /// Started parsing this file.
+    Empezando(PathBuf),
+    /// Finished parsing a file; for consolidated runs the xlsx comes later.
+    Avance,
+    /// An xlsx was written, or failed to be. When reviewing first the report
+    /// is only prepared and nothing is written yet.
+    Resultado {
+        entradas: Vec<PathBuf>,
+        /// The report and the xlsx it went, or goes, to.
+        resultado: Result<(Reporte, PathBuf), String>,
+    },
+    /// A report parsed by `Tarea::Analizar`.
+    Analizado(Result<Reporte, String>),
+    /// A comparison was written, or failed to be.
+    Comparado(Result<Conteo, String>),
+    Fin {
+        cancelado: bool,
+    },
+}
+
+/// Processing running on a background thread.
+pub struct Trabajo {
+    rx: Receiver<Evento>,
+    cancelar: Arc<AtomicBool>,
+    pub total: usize,
+    pub hechos: usize,
+    pub actual: Option<PathBuf>,
+    pub terminado: bool,
+    /// Results are to be reviewed before writing them.
+    pub revisar: bool,
+    /// Compares reports instead of writing them.
+    pub comparar: bool,
+}
+
+/// Parses every file in `entradas`, stopping at the first error. `None` when cancelled.
+fn analizar_todos(
+    entradas: &[PathBuf],
+    opciones: &Opciones,
+    cancelar: &AtomicBool,
+    enviar: &impl Fn(Evento),
+) -> Option<Result<Vec<Reporte>, String>> {
+    let mut reportes = Vec::with_capacity(entradas.len());
+    for entrada in entradas {
+        if cancelar.load(Ordering::Relaxed) {
+            return None;
+        }
+        enviar(Evento::Empezando(entrada.clone()));
+        let reporte = pdf_handling::analizar_con_opciones(entrada, opciones);
+        enviar(Evento::Avance);
+        match reporte {
+            Ok(reporte) => reportes.push(reporte),
+            Err(e) => return Some(Err(format!("{}: {e}", entrada.display()))),
+        }
+    }
+    Some(Ok(reportes))
+}
+
+fn ejecutar(
+    tareas: Vec<Tarea>,
+    opciones: &Opciones,
+    destino: &Destino,
+    revisar: bool,
+    cancelar: &AtomicBool,
+    tx: &Sender<Evento>,
+    ctx: &egui::Context,
+) -> bool {
+    let enviar = |evento| {
+        // The UI may be gone already, nothing to do then.
+        let _ = tx.send(evento);
+        ctx.request_repaint();
+    };
+    let mut usadas = Vec::new();
+    let mut terminar = |mut reporte: Reporte| {
+        let salida = destino.ruta(&reporte, &mut usadas)?;
+        if revisar {
+            pdf_handling::preparar(&mut reporte, opciones)?;
+        } else {
+            reporte = pdf_handling::exportar(reporte, &salida, opciones)?;
+        }
+        Ok((reporte, salida))
+    };
+    for tarea in tareas {
+        if cancelar.load(Ordering::Relaxed) {
+            return true;
+        }
+        match tarea {
+            Tarea::Uno(entrada) => {
+                enviar(Evento::Empezando(entrada.clone()));
+                let resultado =
+                    pdf_handling::analizar_con_opciones(&entrada, opciones).and_then(&mut terminar);
+                enviar(Evento::Avance);
+                if let Err(e) = &resultado {
+                    log::error!("{}: {e}", entrada.display());
+                }
+                enviar(Evento::Resultado {
+                    entradas: vec![entrada],
+                    resultado,
+                });
+            }
+            Tarea::Consolidado(entradas) => {
+                let Some(reportes) = analizar_todos(&entradas, opciones, cancelar, &enviar) else {
+                    return true;
+                };
+                let resultado =
+                    reportes.and_then(|reportes| terminar(Reporte::consolidar(reportes)));
+                if let Err(e) = &resultado {
+                    log::error!("{e}");
+                }
+                enviar(Evento::Resultado {
+                    entradas,
+                    resultado,
+                });
+            }
+            Tarea::Analizar(entrada) => {
+                let Some(reportes) = analizar_todos(&[entrada], opciones, cancelar, &enviar) else {
+                    return true;
+                };
+                let resultado = reportes.map(|mut reportes| reportes.remove(0));
+                if let Err(e) = &resultado {
+                    log::error!("{e}");
+                }
+                enviar(Evento::Analizado(resultado));
+            }
+            Tarea::Comparar {
+                anterior,
+                nuevo,
+                salida,
+            } => {
+                let entradas = [anterior, nuevo];
+                let Some(reportes) = analizar_todos(&entradas, opciones, cancelar, &enviar) else {
+                    return true;
+                };
+                let resultado = reportes.and_then(|reportes| {
+                    comparacion::process_comparacion(&reportes[0], &reportes[1], &salida)
+                });
+                if let Err(e) = &resultado {
+                    log::error!("{e}");
+                }
+                enviar(Evento::Comparado(resultado));
+            }
+        }
+    }
+    false
+}
+
+impl Trabajo {
+    /// With `revisar` the reports are parsed but not written, so they can be
+    /// reviewed first.
+    pub fn iniciar(
+        tareas: Vec<Tarea>,
+        opciones: Opciones,
+        destino: Destino,
+        revisar: bool,
+        ctx: egui::Context,
+    ) -> Self {
+        let (tx, rx) = mpsc::channel();
+        let cancelar = Arc::new(AtomicBool::new(false));
+        let total = tareas.iter().map(Tarea::archivos).sum();
+        let comparar = tareas
+            .iter()
+            .any(|tarea| matches!(tarea, Tarea::Comparar { .. }));
+
+        let cancelar_hilo = cancelar.clone();
+        thread::spawn(move || {
+            let cancelado = ejecutar(
+                tareas,
+                &opciones,
+                &destino,
+                revisar,
+                &cancelar_hilo,
+                &tx,
+                &ctx,
+            );
+            let _ = tx.send(Evento::Fin { cancelado });
+            ctx.request_repaint();
+        });
+
+        Self {
+            rx,
+            cancelar,
+            total,
+            hechos: 0,
+            actual: None,
+            terminado: false,
+            revisar,
+            comparar,
+        }
+    }
+
+    /// Runs a task that writes no reports of its own, like a comparison.
+    pub fn aparte(tarea: Tarea, ctx: egui::Context) -> Self {
+        Self::iniciar(
+            vec![tarea],
+            Opciones::default(),
+            Destino::default(),
+            false,
+            ctx,
+        )
+    }
+
+    /// Stops after the file being parsed now; it can't be interrupted halfway.
+    pub fn cancelar(&self) {
+        self.cancelar.store(true, Ordering::Relaxed);
+    }
+
+    pub fn cancelando(&self) -> bool {
+        self.cancelar.load(Ordering::Relaxed)
+    }
+
+    /// Share of the files already parsed; a file counts once it is done, so
+    /// a long one holds the bar still.
+    pub fn progreso(&self) -> f32 {
+        if self.total == 0 {
+            1.0
+        } else {
+            self.hechos as f32 / self.total as f32
+        }
+    }
+
+    /// Progress bar, the file being parsed and the cancel button.
+    pub fn ui(&self, ui: &mut egui::Ui) {
+        ui.add(egui::ProgressBar::new(self.progreso()).show_percentage());
+        ui.horizontal(|ui| {
+            if let Some(actual) = &self.actual {
+                ui.label(trf(
+                    "Procesando {} (archivo {} de {})",
+                    &[
+                        &actual.file_name().unwrap_or_default().to_string_lossy(),
+                        &(self.hechos + 1),
+                        &self.total,
+                    ],
+                ));
+            }
+            if self.cancelando() {
+                ui.label(tr("Cancelando..."));
+            } else if ui.button(tr("Cancelar")).clicked() {
+                self.cancelar();
+            }
+        });
+    }
+
+    /// Events received since the last call, keeping the progress up to date.
+    pub fn recibir(&mut self) -> Vec<Evento> {
+        let eventos: Vec<Evento> = self.rx.try_iter().collect();
+        for evento in &eventos {
+            match evento {
+                Evento::Empezando(path) => self.actual = Some(path.clone()),
+                Evento::Avance => self.hechos += 1,
+                Evento::Resultado { .. } | Evento::Analizado(_) | Evento::Comparado(_) => {}
+                Evento::Fin { .. } => {
+                    self.actual = None;
+                    self.terminado = true;
+                }
+            }
+        }
+        eventos
+    }
+}
@@ -0,0 +1,100 @@
+use crate::pdf_handling::{SinProcesar, ruta_libre, version_reglas};
+use regex::Captures;
+use std::path::{Path, PathBuf};
+
+/// Step-by-step log of how a report was parsed: text, chunks and matching rules.
+#[derive(Debug)]
+pub struct Traza {
+    contenido: String,
+}
+
+const LINEA: &str = "================================================================";
+
+/// Where the trace of `path` is written, next to it.
+pub fn ruta_traza(path: &Path) -> PathBuf {
+    let stem = path.file_stem().unwrap_or_default().to_string_lossy();
+    ruta_libre(path.with_file_name(format!("{stem}_traza.txt")))
+}
+
+impl Traza {
+    /// Starts a trace for `path`, with the versions needed to reproduce it.
+    pub fn nueva(path: &Path) -> Self {
+        Self {
+            contenido: format!(
+                "Archivo: {}\nreps-sia {}\nReglas: {}\n",
+                path.display(),
+                env!("CARGO_PKG_VERSION"),
+                version_reglas()
+            ),
+        }
+    }
+
+    fn titulo(&mut self, titulo: &str) {
+        self.contenido += &format!("\n{LINEA}\n{titulo}\n{LINEA}\n");
+    }
+
+    /// Raw text as extracted, split by page when the page starts are known.
+    pub fn texto(&mut self, texto: &str, inicios_de_pagina: &[usize]) {
+        self.titulo("TEXTO EXTRAÍDO");
+        if inicios_de_pagina.is_empty() {
+            self.contenido += texto;
+            self.contenido.push('\n');
+            return;
+        }
+        for (i, &inicio) in inicios_de_pagina.iter().enumerate() {
+            let fin = inicios_de_pagina.get(i + 1).copied().unwrap_or(texto.len());
+            self.contenido += &format!("--- Página {} ---\n", i + 1);
+            self.contenido += texto.get(inicio..fin).unwrap_or_default();
+            self.contenido.push('\n');
+        }
+    }
+
+    pub fn secciones(&mut self, secciones: &[&str]) {
+        self.titulo(&format!("SECCIONES ({})", secciones.len()));
+        for (i, seccion) in secciones.iter().enumerate() {
+            self.contenido += &format!("--- Sección {} ---\n{seccion}\n", i + 1);
+        }
+    }
+
+    pub fn anotaciones(&mut self, anotaciones: &str) {
+        self.titulo("ANOTACIONES");
+        self.contenido += anotaciones;
+        self.contenido.push('\n');
+    }
+
+    pub fn chunk(&mut self, posicion: usize, chunk: &str, paginas: Option<(usize, usize)>) {
+        let paginas = match paginas {
+            Some((primera, ultima)) if primera == ultima => format!(", página {primera}"),
+            Some((primera, ultima)) => format!(", páginas {primera}-{ultima}"),
+            None => String::new(),
+        };
+        self.titulo(&format!("SOLICITUD {posicion}{paginas}"));
+        self.contenido += chunk;
+        self.contenido.push('\n');
+    }
+
+    /// Which rule matched the last chunk and every group it captured.
+    pub fn coincidencia(&mut self, regla: &str, captures: &Captures) {
+        self.contenido += &format!("\n>> Coincide con {regla}\n");
+        for (i, grupo) in captures.iter().enumerate().skip(1) {
+            let valor = grupo.map_or("<sin captura>", |m| m.as_str());
+            self.contenido += &format!("   {i}: {valor:?}\n");
+        }
+    }
+
+    pub fn sin_coincidencia(&mut self) {
+        self.contenido += "\n>> Ninguna regla coincide\n";
+    }
+
+    pub fn sin_procesar(&mut self, unhandled: &[SinProcesar]) {
+        self.titulo(&format!("SIN PROCESAR ({})", unhandled.len()));
+        for chunk in unhandled {
+            self.contenido += &format!("Solicitud {}: {}\n", chunk.posicion, chunk.motivo);
+        }
+    }
+
+    pub fn escribir(&self, path: &Path) -> Result<(), String> {
+        std::fs::write(path, &self.contenido)
+            .map_err(|e| format!("Error writing trace '{}': {e}", path.display()))
+    }
+}
@@ -0,0 +1,328 @@
+use crate::pdf_handling::{self, Opciones, Reporte};
+use crate::salida::Destino;
+use chrono::Local;
+use serde::{Deserialize, Serialize};
+use std::collections::{BTreeMap, HashMap};
+use std::path::{Path, PathBuf};
+use std::sync::Arc;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::mpsc::{self, Receiver};
+use std::thread;
+use std::time::{Duration, SystemTime};
+
+/// File in the output folder that records every PDF already handled.
+const REGISTRO: &str = "reps-sia-vigilancia.toml";
+
+/// Seconds between looks at the folder unless told otherwise.
+pub const INTERVALO_PREDETERMINADO: u64 = 5;
+
+/// A PDF of the watched folder that was processed, or failed.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+struct Hecho {
+    archivo: String,
+    fecha: String,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    salida: Option<PathBuf>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    error: Option<String>,
+}
+
+/// PDFs handled so far, by the SHA-256 of their contents.
+#[derive(Debug, Default, Serialize, Deserialize)]
+struct Registro {
+    #[serde(default)]
+    procesados: BTreeMap<String, Hecho>,
+}
+
+/// Size and modification time, to tell when a file stopped changing.
+type Firma = (u64, SystemTime);
+
+fn firma(path: &Path) -> Option<Firma> {
+    let meta = std::fs::metadata(path).ok()?;
+    Some((meta.len(), meta.modified().ok()?))
+}
+
+/// Outcome of processing a PDF that showed up in the folder.
+#[derive(Debug)]
+pub struct Procesado {
+    pub entrada: PathBuf,
+    pub resultado: Result<(Reporte, PathBuf), String>,
+}
+
+/// Watched folder whose new PDFs are processed on their own.
+pub struct Vigilancia {
+    entrada: PathBuf,
+    destino: Destino,
+    opciones: Opciones,
+    ruta_registro: PathBuf,
+    registro: Registro,
+    /// Files seen in the last look that hadn't been handled, to process them
+    /// only once they stop changing (e.g. when a download finishes).
+    vistos: HashMap<PathBuf, Firma>,
+    /// Files already handled or skipped in this session, with how they were then.
+    revisados: HashMap<PathBuf, Firma>,
+}
+
+impl Vigilancia {
+    /// Watches `entrada`, writing to `destino`'s folder. The record of handled
+    /// files goes there too, or in `entrada` if the excel go next to the reports.
+    pub fn nueva(entrada: PathBuf, destino: Destino, opciones: Opciones) -> Result<Self, String> {
+        if !entrada.is_dir() {
+            return Err(format!("'{}' is not a folder", entrada.display()));
+        }
+        destino.validar()?;
+        let carpeta_registro = match &destino.carpeta {
+            Some(carpeta) if !carpeta.as_os_str().is_empty() => carpeta.clone(),
+            _ => entrada.clone(),
+        };
+        std::fs::create_dir_all(&carpeta_registro)
+            .map_err(|e| format!("Error creating '{}': {e}", carpeta_registro.display()))?;
+        let ruta_registro = carpeta_registro.join(REGISTRO);
+        let registro = match std::fs::read_to_string(&ruta_registro) {
+            Ok(texto) => toml::from_str(&texto)
+                .map_err(|e| format!("Error reading '{}': {e}", ruta_registro.display()))?,
+            Err(e) if e.kind() == std::io::ErrorKind::NotFound => Registro::default(),
+            Err(e) => return Err(format!("Error reading '{}': {e}", ruta_registro.display())),
+        };
+        Ok(Self {
+            entrada,
+            destino,
+            opciones,
+            ruta_registro,
+            registro,
+            vistos: HashMap::new(),
+            revisados: HashMap::new(),
+        })
+    }
+
+    pub fn entrada(&self) -> &Path {
+        &self.entrada
+    }
+
+    fn guardar_registro(&self) -> Result<(), String> {
+        let texto = toml::to_string(&self.registro)
+            .map_err(|e| format!("Error writing '{}': {e}", self.ruta_registro.display()))?;
+        std::fs::write(&self.ruta_registro, texto)
+            .map_err(|e| format!("Error writing '{}': {e}", self.ruta_registro.display()))
+    }
+
+    /// PDFs directly in the watched folder, which haven't been handled in
+    /// the state they are now.
+    fn candidatos(&self) -> Result<Vec<(PathBuf, Firma)>, String> {
+        let entradas = std::fs::read_dir(&self.entrada)
+            .map_err(|e| format!("Error reading '{}': {e}", self.entrada.display()))?;
+        let mut candidatos: Vec<(PathBuf, Firma)> = entradas
+            .filter_map(|entrada| entrada.ok().map(|entrada| entrada.path()))
+            .filter(|path| {
+                path.is_file()
+                    && path
+                        .extension()
+                        .is_some_and(|ext| ext.eq_ignore_ascii_case("pdf"))
+            })
+            .filter_map(|path| firma(&path).map(|firma| (path, firma)))
+            .filter(|(path, firma)| self.revisados.get(path) != Some(firma))
+            .collect();
+        candidatos.sort();
+        Ok(candidatos)
+    }
+
+    /// Looks at the folder once, processing the new PDFs that stopped
+    /// changing since the last look.
+    pub fn revisar(&mut self) -> Result<Vec<Procesado>, String> {
+        let candidatos = self.candidatos()?;
+        let mut procesados = Vec::new();
+        let mut vistos = HashMap::new();
+        for (path, firma) in candidatos {
+            if self.vistos.get(&path) != Some(&firma) {
+                vistos.insert(path, firma);
+                continue;
+            }
+            let bytes = match std::fs::read(&path) {
+                Ok(bytes) => bytes,
+                // Probably still being written, try again on the next look.
+                Err(_) => {
+                    vistos.insert(path, firma);
+                    continue;
+                }
+            };
+            let sha256 = pdf_handling::sha256(&bytes);
+            self.revisados.insert(path.clone(), firma);
+            if self.registro.procesados.contains_key(&sha256) {
+                continue;
+            }
+
+            let resultado = self.procesar(&path);
+            let archivo = path
+                .file_name()
+                .unwrap_or_default()
+                .to_string_lossy()
+                .to_string();
+            let hecho = match &resultado {
+                Ok((_, salida)) => Hecho {
+                    archivo,
+                    fecha: Local::now().to_rfc3339(),
+                    salida: Some(salida.clone()),
+                    error: None,
+                },
+                Err(e) => {
+                    log::error!("{}: {e}", path.display());
+                    Hecho {
+                        archivo,
+                        fecha: Local::now().to_rfc3339(),
+                        salida: None,
+                        error: Some(e.clone()),
+                    }
+                }
+            };
+            self.registro.procesados.insert(sha256, hecho);
+            procesados.push(Procesado {
+                entrada: path,
+                resultado,
+            });
+        }
+        self.vistos = vistos;
+        // What was written is still reported; at worst it is processed again
+        // in a later session.
+        if !procesados.is_empty()
+            && let Err(e) = self.guardar_registro()
+        {
+            log::error!("{e}");
+        }
+        Ok(procesados)
+    }
+
+    fn procesar(&self, path: &Path) -> Result<(Reporte, PathBuf), String> {
+        let reporte = pdf_handling::analizar_con_opciones(path, &self.opciones)?;
+        // Each file is its own run, so overwriting replaces the previous excel.
+        let salida = self.destino.ruta(&reporte, &mut Vec::new())?;
+        let reporte = pdf_handling::exportar(reporte, &salida, &self.opciones)?;
+        Ok((reporte, salida))
+    }
+}
+
+/// Runs a `Vigilancia` for the window, handing back each result.
+pub struct Vigilante {
+    rx: Receiver<Result<Procesado, String>>,
+    detener: Arc<AtomicBool>,
+    entrada: PathBuf,
+}
+
+impl Vigilante {
+    /// Looks at the folder every `intervalo`, calling `avisar` after each
+    /// result so the window repaints.
+    pub fn iniciar(
+        mut vigilancia: Vigilancia,
+        intervalo: Duration,
+        avisar: impl Fn() + Send + 'static,
+    ) -> Self {
+        let (tx, rx) = mpsc::channel();
+        let detener = Arc::new(AtomicBool::new(false));
+        let entrada = vigilancia.entrada().to_path_buf();
+
+        let detener_hilo = detener.clone();
+        thread::spawn(move || {
+            while !detener_hilo.load(Ordering::Relaxed) {
+                let resultados = match vigilancia.revisar() {
+                    Ok(procesados) => procesados.into_iter().map(Ok).collect(),
+                    Err(e) => vec![Err(e)],
+                };
+                for resultado in resultados {
+                    // The window is gone, stop watching.
+                    if tx.send(resultado).is_err() {
+                        return;
+                    }
+                    avisar();
+                }
+                thread::sleep(intervalo);
+            }
+        });
+
+        Self {
+            rx,
+            detener,
+            entrada,
+        }
+    }
+
+    pub fn entrada(&self) -> &Path {
+        &self.entrada
+    }
+
+    /// Stops watching once the current file, if any, is done.
+    pub fn detener(&self) {
+        self.detener.store(true, Ordering::Relaxed);
+    }
+
+    /// Results since the last call; an `Err` is a problem with the folder itself.
+    pub fn recibir(&self) -> Vec<Result<Procesado, String>> {
+        self.rx.try_iter().collect()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Empty watched folder and the folder the excel go to.
+    fn carpetas(nombre: &str) -> (PathBuf, PathBuf) {
+        let raiz = std::env::temp_dir().join(format!("reps-sia-{}-{nombre}", std::process::id()));
+        let _ = std::fs::remove_dir_all(&raiz);
+        let (entrada, salida) = (raiz.join("entrada"), raiz.join("salida"));
+        std::fs::create_dir_all(&entrada).unwrap();
+        (entrada, salida)
+    }
+
+    fn vigilar(entrada: &Path, salida: &Path) -> Vigilancia {
+        let destino = Destino {
+            carpeta: Some(salida.to_path_buf()),
+            ..Default::default()
+        };
+        Vigilancia::nueva(entrada.to_path_buf(), destino, Opciones::default()).unwrap()
+    }
+
+    fn entradas(procesados: Vec<Procesado>) -> Vec<PathBuf> {
+        procesados
+            .into_iter()
+            .map(|procesado| procesado.entrada)
+            .collect()
+    }
+
+    #[test]
+    fn revisar_espera_a_que_el_archivo_deje_de_cambiar() {
+        let (entrada, salida) = carpetas("vigilancia-estable");
+        let pdf = entrada.join("a.pdf");
+        let mut vigilancia = vigilar(&entrada, &salida);
+
+        std::fs::write(&pdf, b"a medias").unwrap();
+        let primera = vigilancia.revisar().unwrap();
+        std::fs::write(&pdf, b"a medias, ya completo").unwrap();
+        let segunda = vigilancia.revisar().unwrap();
+        let tercera = vigilancia.revisar().unwrap();
+        let cuarta = vigilancia.revisar().unwrap();
+        let _ = std::fs::remove_dir_all(entrada.parent().unwrap());
+
+        assert!(primera.is_empty());
+        assert!(segunda.is_empty());
+        assert_eq!(entradas(tercera), vec![pdf]);
+        assert!(cuarta.is_empty());
+    }
+
+    #[test]
+    fn revisar_salta_los_archivos_con_contenido_ya_procesado() {
+        let (entrada, salida) = carpetas("vigilancia-hash");
+        std::fs::write(entrada.join("a.pdf"), b"reporte").unwrap();
+        let mut vigilancia = vigilar(&entrada, &salida);
+        vigilancia.revisar().unwrap();
+        let procesados = vigilancia.revisar().unwrap();
+
+        // A renamed copy is skipped, also by a new watch reading the record.
+        std::fs::write(entrada.join("b.pdf"), b"reporte").unwrap();
+        let mut otra = vigilar(&entrada, &salida);
+        otra.revisar().unwrap();
+        let copia = otra.revisar().unwrap();
+        let _ = std::fs::remove_dir_all(entrada.parent().unwrap());
+
+        assert_eq!(entradas(procesados), vec![entrada.join("a.pdf")]);
+        assert!(copia.is_empty());
+    }
+}
@@ -0,0 +1,530 @@
+use crate::idioma::{tr, trf};
+use crate::pdf_handling::{
+    ACM, ANOTACIONES, CAMPOS_PARCIALES, CEA, CS, Campo, RTG, Reporte, SinProcesar, Solicitud,
+    campos_del_tipo,
+};
+use eframe::egui;
+use std::cmp::Ordering;
+use std::path::PathBuf;
+
+/// Types a request can be moved to.
+const TIPOS: [&str; 4] = [CEA, CS, ACM, RTG];
+
+/// Labels longer than this are cut in the table; the full text is on hover.
+const LARGO_CELDA: usize = 60;
+
+/// A parsed report waiting to be reviewed before it is written.
+pub struct Pendiente {
+    pub entradas: Vec<PathBuf>,
+    pub salida: PathBuf,
+    pub reporte: Reporte,
+}
+
+struct Edicion {
+    fila: usize,
+    campo: Campo,
+    valor: String,
+}
+
+#[derive(Clone, PartialEq)]
+enum Pestana {
+    Tipo(String),
+    SinProcesar,
+}
+
+/// Form to turn an unhandled chunk into a request.
+struct Resolucion {
+    /// Index of the chunk in `Reporte::sin_procesar`.
+    indice: usize,
+    tipo: &'static str,
+    valores: Vec<(Campo, String)>,
+}
+
+impl Resolucion {
+    fn nueva(indice: usize, chunk: &SinProcesar) -> Self {
+        let mut resolucion = Self {
+            indice,
+            tipo: CEA,
+            valores: CAMPOS_PARCIALES
+                .into_iter()
+                .zip(chunk.campos_parciales())
+                .collect(),
+        };
+        resolucion.cambiar_tipo(CEA);
+        resolucion
+    }
+
+    /// Shows the fields of `tipo`, keeping what was already typed.
+    fn cambiar_tipo(&mut self, tipo: &'static str) {
+        self.tipo = tipo;
+        self.valores = campos_del_tipo(tipo)
+            .into_iter()
+            .filter(|campo| *campo != Campo::Paginas)
+            .map(|campo| {
+                let valor = self
+                    .valores
+                    .iter()
+                    .find(|(c, _)| *c == campo)
+                    .map(|(_, valor)| valor.clone())
+                    .unwrap_or_default();
+                (campo, valor)
+            })
+            .collect();
+    }
+}
+
+enum Cambio {
+    Mover(usize, &'static str),
+    Excluir(usize),
+}
+
+/// Applies what was clicked in the table of `tipo` this frame. The row of a
+/// move or exclusion is in the order shown, so it goes before sorting.
+fn aplicar(
+    reporte: &mut Reporte,
+    tipo: &str,
+    cambio: Option<Cambio>,
+    nuevo_orden: Option<(Campo, bool)>,
+) {
+    let solicitudes = reporte.solicitudes.entry(tipo.to_string()).or_default();
+    match cambio {
+        Some(Cambio::Excluir(fila)) => {
+            solicitudes.remove(fila);
+        }
+        Some(Cambio::Mover(fila, destino)) => {
+            let mut sol = solicitudes.remove(fila);
+            sol.ajustar_a_tipo(destino);
+            reporte
+                .solicitudes
+                .entry(destino.to_string())
+                .or_default()
+                .push(sol);
+        }
+        None => {}
+    }
+    if let Some((campo, ascendente)) = nuevo_orden
+        && let Some(solicitudes) = reporte.solicitudes.get_mut(tipo)
+    {
+        solicitudes.sort_by(|a, b| {
+            let orden = comparar(a, b, campo);
+            if ascendente { orden } else { orden.reverse() }
+        });
+    }
+    // Empty types would end up as empty sheets.
+    reporte
+        .solicitudes
+        .retain(|_, solicitudes| !solicitudes.is_empty());
+}
+
+pub enum Accion {
+    Exportar,
+    Descartar,
+    Mensaje(String),
+}
+
+/// Editable tables of the parsed requests, a tab per type.
+#[derive(Default)]
+pub struct VistaPrevia {
+    pub pendientes: Vec<Pendiente>,
+    actual: usize,
+    pestana: Option<Pestana>,
+    orden: Option<(Campo, bool)>,
+    edicion: Option<Edicion>,
+    resolucion: Option<Resolucion>,
+}
+
+fn comparar(a: &Solicitud, b: &Solicitud, campo: Campo) -> Ordering {
+    match campo {
+        Campo::FechaDeSolicitud => a.fecha_de_solicitud.cmp(&b.fecha_de_solicitud),
+        Campo::Identificacion => a.identificacion.cmp(&b.identificacion),
+        Campo::Adjuntos => a.adjuntos.cmp(&b.adjuntos),
+        Campo::Paginas => a.paginas.cmp(&b.paginas),
+        _ => a.valor(campo).cmp(&b.valor(campo)),
+    }
+}
+
+fn corto(texto: &str) -> String {
+    if texto.chars().count() > LARGO_CELDA {
+        texto.chars().take(LARGO_CELDA).chain("…".chars()).collect()
+    } else {
+        texto.to_string()
+    }
+}
+
+impl VistaPrevia {
+    pub fn agregar(&mut self, pendiente: Pendiente) {
+        self.pendientes.push(pendiente);
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.pendientes.is_empty()
+    }
+
+    /// Hands over every pending report, leaving the view empty.
+    pub fn tomar(&mut self) -> Vec<Pendiente> {
+        let pendientes = std::mem::take(&mut self.pendientes);
+        *self = Self::default();
+        pendientes
+    }
+
+    fn tipos(reporte: &Reporte) -> Vec<String> {
+        let mut tipos: Vec<String> = reporte
+            .solicitudes
+            .keys()
+            .filter(|tipo| tipo.as_str() != ANOTACIONES)
+            .cloned()
+            .collect();
+        tipos.sort();
+        tipos
+    }
+
+    pub fn ui(&mut self, ui: &mut egui::Ui) -> Option<Accion> {
+        if self.pendientes.is_empty() {
+            ui.label(tr("No hay reportes por revisar"));
+            return None;
+        }
+        let mut accion = None;
+
+        if self.pendientes.len() > 1 {
+            ui.horizontal(|ui| {
+                ui.label(tr("Excel:"));
+                let anterior = self.actual;
+                egui::ComboBox::from_id_salt("pendiente")
+                    .selected_text(self.pendientes[self.actual].salida.display().to_string())
+                    .show_ui(ui, |ui| {
+                        for (i, pendiente) in self.pendientes.iter().enumerate() {
+                            ui.selectable_value(
+                                &mut self.actual,
+                                i,
+                                pendiente.salida.display().to_string(),
+                            );
+                        }
+                    });
+                if self.actual != anterior {
+                    self.pestana = None;
+                    self.edicion = None;
+                    self.resolucion = None;
+                }
+            });
+        }
+
+        let reporte = &mut self.pendientes[self.actual].reporte;
+        let mut pestanas: Vec<(Pestana, String)> = Self::tipos(reporte)
+            .into_iter()
+            .map(|tipo| {
+                let n = reporte.solicitudes.get(&tipo).map_or(0, Vec::len);
+                let titulo = format!("{} ({n})", tr(&tipo));
+                (Pestana::Tipo(tipo), titulo)
+            })
+            .collect();
+        if !reporte.sin_procesar.is_empty() {
+            let titulo = trf("Sin procesar ({})", &[&reporte.sin_procesar.len()]);
+            pestanas.push((Pestana::SinProcesar, titulo));
+        }
+        if self
+            .pestana
+            .as_ref()
+            .is_none_or(|pestana| pestanas.iter().all(|(p, _)| p != pestana))
+        {
+            self.pestana = pestanas.first().map(|(pestana, _)| pestana.clone());
+            self.edicion = None;
+            self.resolucion = None;
+        }
+
+        ui.horizontal_wrapped(|ui| {
+            for (pestana, titulo) in pestanas {
+                let seleccionada = self.pestana.as_ref() == Some(&pestana);
+                if ui.selectable_label(seleccionada, titulo).clicked() && !seleccionada {
+                    self.pestana = Some(pestana);
+                    self.edicion = None;
+                }
+            }
+        });
+        ui.separator();
+
+        match self.pestana.clone() {
+            Some(Pestana::Tipo(tipo)) => accion = self.tabla(ui, &tipo),
+            Some(Pestana::SinProcesar) => accion = self.sin_procesar(ui),
+            None => {
+                ui.label(tr("El reporte no tiene solicitudes"));
+            }
+        }
+
+        ui.separator();
+        ui.horizontal(|ui| {
+            if ui.button(tr("Exportar")).clicked() {
+                accion = Some(Accion::Exportar);
+            }
+            if ui.button(tr("Descartar")).clicked() {
+                accion = Some(Accion::Descartar);
+            }
+        });
+        accion
+    }
+
+    fn tabla(&mut self, ui: &mut egui::Ui, tipo: &str) -> Option<Accion> {
+        let campos = campos_del_tipo(tipo);
+        let reporte = &mut self.pendientes[self.actual].reporte;
+        let mut mensaje = None;
+        let mut cambio = None;
+        let mut nuevo_orden = None;
+
+        egui::ScrollArea::both()
+            .id_salt("vista_previa")
+            .max_height(400.0)
+            .show(ui, |ui| {
+                egui::Grid::new("vista_previa")
+                    .striped(true)
+                    .num_columns(campos.len() + 1)
+                    .show(ui, |ui| {
+                        for &campo in &campos {
+                            let flecha = match self.orden {
+                                Some((c, true)) if c == campo => " ▲",
+                                Some((c, false)) if c == campo => " ▼",
+                                _ => "",
+                            };
+                            if ui
+                                .button(format!("{}{flecha}", campo.encabezado()))
+                                .clicked()
+                            {
+                                let ascendente =
+                                    !matches!(self.orden, Some((c, true)) if c == campo);
+                                nuevo_orden = Some((campo, ascendente));
+                            }
+                        }
+                        ui.strong(tr("Acciones"));
+                        ui.end_row();
+
+                        let Some(solicitudes) = reporte.solicitudes.get_mut(tipo) else {
+                            return;
+                        };
+                        for (fila, sol) in solicitudes.iter_mut().enumerate() {
+                            for &campo in &campos {
+                                let editando = self
+                                    .edicion
+                                    .as_ref()
+                                    .is_some_and(|e| e.fila == fila && e.campo == campo);
+                                if editando {
+                                    let edicion = self.edicion.as_mut().expect("editing");
+                                    let respuesta = ui.text_edit_singleline(&mut edicion.valor);
+                                    respuesta.request_focus();
+                                    if respuesta.lost_focus() {
+                                        let edicion = self.edicion.take().expect("editing");
+                                        if sol.valor(campo).unwrap_or_default() != edicion.valor
+                                            && let Err(e) = sol.corregir(campo, &edicion.valor)
+                                        {
+                                            mensaje = Some(format!("Error: {e}"));
+                                        }
+                                    }
+                                    continue;
+                                }
+
+                                let valor = sol.valor(campo).unwrap_or_default();
+                                let texto = match sol.originales.get(&campo) {
+                                    Some(_) => egui::RichText::new(corto(&valor))
+                                        .color(ui.visuals().hyperlink_color),
+                                    None => egui::RichText::new(corto(&valor)),
+                                };
+                                let editable =
+                                    !matches!(campo, Campo::NumeroSolicitud | Campo::Paginas);
+                                let mut respuesta =
+                                    ui.add(egui::Label::new(texto).sense(if editable {
+                                        egui::Sense::click()
+                                    } else {
+                                        egui::Sense::hover()
+                                    }));
+                                if let Some(original) = sol.originales.get(&campo) {
+                                    respuesta = respuesta
+                                        .on_hover_text(trf("Valor en el PDF: {}", &[original]));
+                                } else if valor.chars().count() > LARGO_CELDA {
+                                    respuesta = respuesta.on_hover_text(&valor);
+                                }
+                                if respuesta.clicked() {
+                                    self.edicion = Some(Edicion { fila, campo, valor });
+                                }
+                            }
+
+                            ui.horizontal(|ui| {
+                                egui::ComboBox::from_id_salt(("mover", fila))
+                                    .selected_text(tr("Mover a"))
+                                    .show_ui(ui, |ui| {
+                                        for destino in TIPOS.into_iter().filter(|t| *t != tipo) {
+                                            if ui.selectable_label(false, tr(destino)).clicked() {
+                                                cambio = Some(Cambio::Mover(fila, destino));
+                                            }
+                                        }
+                                    });
+                                if ui.button(tr("Excluir")).clicked() {
+                                    cambio = Some(Cambio::Excluir(fila));
+                                }
+                            });
+                            ui.end_row();
+                        }
+                    });
+            });
+
+        if cambio.is_some() || nuevo_orden.is_some() {
+            self.edicion = None;
+        }
+        if nuevo_orden.is_some() {
+            self.orden = nuevo_orden;
+        }
+        aplicar(reporte, tipo, cambio, nuevo_orden);
+        mensaje.map(Accion::Mensaje)
+    }
+
+    fn sin_procesar(&mut self, ui: &mut egui::Ui) -> Option<Accion> {
+        let reporte = &mut self.pendientes[self.actual].reporte;
+        let mut mensaje = None;
+
+        ui.label(tr(
+            "Elija una solicitud que no se pudo leer para completarla a mano:",
+        ));
+        egui::ScrollArea::vertical()
+            .id_salt("sin_procesar")
+            .max_height(120.0)
+            .show(ui, |ui| {
+                for (indice, chunk) in reporte.sin_procesar.iter().enumerate() {
+                    let seleccionado = self.resolucion.as_ref().is_some_and(|r| r.indice == indice);
+                    let paginas = match chunk.paginas {
+                        Some((desde, hasta)) if desde != hasta => {
+                            trf(" (págs. {}-{})", &[&desde, &hasta])
+                        }
+                        Some((pagina, _)) => trf(" (pág. {})", &[&pagina]),
+                        None => String::new(),
+                    };
+                    let etiqueta = corto(&format!("{}{paginas}: {}", chunk.posicion, chunk.motivo));
+                    if ui.selectable_label(seleccionado, etiqueta).clicked() && !seleccionado {
+                        self.resolucion = Some(Resolucion::nueva(indice, chunk));
+                    }
+                }
+            });
+
+        let Some(resolucion) = &mut self.resolucion else {
+            return None;
+        };
+        let Some(chunk) = reporte.sin_procesar.get(resolucion.indice) else {
+            self.resolucion = None;
+            return None;
+        };
+        ui.separator();
+        egui::ScrollArea::vertical()
+            .id_salt("texto_sin_procesar")
+            .max_height(200.0)
+            .show(ui, |ui| {
+                ui.add(
+                    egui::TextEdit::multiline(&mut chunk.texto.trim())
+                        .code_editor()
+                        .desired_width(f32::INFINITY),
+                );
+            });
+
+        ui.horizontal(|ui| {
+            ui.label(tr("Tipo de solicitud:"));
+            let mut tipo = resolucion.tipo;
+            egui::ComboBox::from_id_salt("tipo_sin_procesar")
+                .selected_text(tr(tipo))
+                .show_ui(ui, |ui| {
+                    for destino in TIPOS {
+                        ui.selectable_value(&mut tipo, destino, tr(destino));
+                    }
+                });
+            if tipo != resolucion.tipo {
+                resolucion.cambiar_tipo(tipo);
+            }
+        });
+        egui::Grid::new("resolucion").num_columns(2).show(ui, |ui| {
+            for (campo, valor) in &mut resolucion.valores {
+                ui.label(campo.encabezado());
+                ui.add(egui::TextEdit::singleline(valor).desired_width(400.0));
+                ui.end_row();
+            }
+        });
+
+        if ui
+            .button(trf("Agregar a {}", &[&tr(resolucion.tipo)]))
+            .clicked()
+        {
+            match chunk.resolver(resolucion.tipo, &resolucion.valores) {
+                Ok(sol) => {
+                    let tipo = resolucion.tipo;
+                    mensaje = Some(trf(
+                        "Solicitud {} agregada a {}",
+                        &[&sol.numero_solicitud, &tr(tipo)],
+                    ));
+                    reporte.sin_procesar.remove(resolucion.indice);
+                    reporte
+                        .solicitudes
+                        .entry(tipo.to_string())
+                        .or_default()
+                        .push(sol);
+                    self.resolucion = None;
+                }
+                Err(e) => mensaje = Some(format!("Error: {e}")),
+            }
+        }
+        mensaje.map(Accion::Mensaje)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn pendiente(salida: &str) -> Pendiente {
+        Pendiente {
+            entradas: vec![PathBuf::from("reporte.pdf")],
+            salida: PathBuf::from(salida),
+            reporte: Reporte::default(),
+        }
+    }
+
+    fn numeros(reporte: &Reporte, tipo: &str) -> Vec<String> {
+        reporte.solicitudes[tipo]
+            .iter()
+            .map(|sol| sol.numero_solicitud.clone())
+            .collect()
+    }
+
+    #[test]
+    fn aplicar_excluye_la_fila_mostrada_aunque_se_ordene_a_la_vez() {
+        let mut reporte = Reporte::default();
+        reporte.solicitudes.insert(
+            CEA.to_string(),
+            ["CEA3", "CEA1", "CEA2"]
+                .into_iter()
+                .map(|numero| Solicitud {
+                    numero_solicitud: numero.to_string(),
+                    ..Default::default()
+                })
+                .collect(),
+        );
+
+        aplicar(
+            &mut reporte,
+            CEA,
+            Some(Cambio::Excluir(0)),
+            Some((Campo::NumeroSolicitud, true)),
+        );
+
+        assert_eq!(numeros(&reporte, CEA), ["CEA1", "CEA2"]);
+    }
+
+    #[test]
+    fn tomar_entrega_los_pendientes_y_vacia_la_vista() {
+        let mut vista = VistaPrevia::default();
+        vista.agregar(pendiente("uno.xlsx"));
+        vista.agregar(pendiente("dos.xlsx"));
+        vista.actual = 1;
+
+        let pendientes = vista.tomar();
+
+        let salidas: Vec<_> = pendientes.iter().map(|p| p.salida.clone()).collect();
+        assert_eq!(
+            salidas,
+            [PathBuf::from("uno.xlsx"), PathBuf::from("dos.xlsx")]
+        );
+        assert!(vista.is_empty());
+        assert_eq!(vista.actual, 0);
+    }
+}